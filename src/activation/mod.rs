@@ -0,0 +1,98 @@
+use solana_sdk::pubkey::Pubkey;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// unix timestamps are ~1.7e9 while mainnet slots are still well below 1e9
+const TIMESTAMP_THRESHOLD: u64 = 1_000_000_000;
+
+// How a pool's activation point is expressed, shared by every Meteora program: 0 = slot, 1 = timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivationType {
+    Slot,
+    Timestamp,
+}
+
+impl ActivationType {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ActivationType::Slot),
+            1 => Some(ActivationType::Timestamp),
+            _ => None,
+        }
+    }
+
+    // Last resort when neither the instruction nor a config account tells us the type.
+    pub fn guess(point: u64) -> Self {
+        if point >= TIMESTAMP_THRESHOLD {
+            ActivationType::Timestamp
+        } else {
+            ActivationType::Slot
+        }
+    }
+}
+
+// What a pool-creation instruction tells us about when the pool opens for trading.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ActivationInfo {
+    pub point: Option<u64>,
+    pub activation_type: Option<ActivationType>,
+    // config account holding the activation type / duration when the instruction doesn't
+    pub config: Option<Pubkey>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Activation {
+    pub point: u64,
+    pub activation_type: ActivationType,
+}
+
+// Estimates the current slot from the last slot seen in the stream.
+#[derive(Debug, Clone, Copy)]
+pub struct SlotClock {
+    slot: u64,
+    observed_at: Instant,
+    slot_duration: Duration,
+}
+
+impl SlotClock {
+    pub fn new(slot_duration: Duration) -> Self {
+        SlotClock {
+            slot: 0,
+            observed_at: Instant::now(),
+            slot_duration,
+        }
+    }
+
+    pub fn observe(&mut self, slot: u64) {
+        if slot > self.slot {
+            self.slot = slot;
+            self.observed_at = Instant::now();
+        }
+    }
+
+    pub fn estimated_slot(&self) -> u64 {
+        let elapsed = self.observed_at.elapsed().as_micros();
+        let slot_micros = self.slot_duration.as_micros().max(1);
+        self.slot + (elapsed / slot_micros) as u64
+    }
+
+    // How long to wait before sending so the swap lands once the pool is active,
+    // zero when it already is. An activation too far out to compute is `Duration::MAX`.
+    pub fn time_until(&self, activation: &Activation, lead_time: Duration) -> Duration {
+        let until = match activation.activation_type {
+            ActivationType::Slot => {
+                let slots = activation.point.saturating_sub(self.estimated_slot());
+                u32::try_from(slots)
+                    .ok()
+                    .and_then(|slots| self.slot_duration.checked_mul(slots))
+                    .unwrap_or(Duration::MAX)
+            }
+            ActivationType::Timestamp => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                Duration::from_secs(activation.point).saturating_sub(now)
+            }
+        };
+        until.saturating_sub(lead_time)
+    }
+}
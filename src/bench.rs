@@ -1,5 +1,6 @@
 use crate::config::PingThingsArgs;
-use crate::meteora::SwapData;
+use crate::landing::{collect_metrics, Landing, LandingTracker};
+use crate::meteora::SwapOrder;
 use crate::tx_senders::jito::JitoBundleStatusResponse;
 use crate::tx_senders::solana_rpc::TxMetrics;
 use crate::tx_senders::transaction::TransactionConfig;
@@ -11,6 +12,7 @@ use reqwest::Client;
 use serde::Deserialize;
 use serde_json::json;
 use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::hash::Hash;
 use solana_sdk::pubkey::Pubkey;
@@ -20,21 +22,35 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
+// Resolves to the slot a swap first landed in successfully, None when no send made it.
+pub type SwapLanding = JoinHandle<Option<u64>>;
+
+// What one sender did with a swap, `elapsed_ms` counts from the start of the swap.
+struct SendOutcome {
+    rpc_name: String,
+    tx_result: TxResult,
+    landed: Option<Landing>,
+    elapsed_ms: u64,
+}
+
 #[derive(Clone)]
 pub struct Bench {
     config: PingThingsArgs,
     tx_subscribe_sender: tokio::sync::mpsc::Sender<TxMetrics>,
     rpcs: Vec<Arc<dyn TxSender>>,
     client: Client,
+    landing: LandingTracker,
 }
 
 impl Bench {
     pub fn new(config: PingThingsArgs) -> Self {
         let (tx_subscribe_sender, tx_subscribe_receiver) = tokio::sync::mpsc::channel(100);
+        tokio::spawn(collect_metrics(tx_subscribe_receiver));
         let tx_config: TransactionConfig = config.clone().into();
         let client = Client::new();
 
@@ -45,27 +61,39 @@ impl Bench {
             .map(|(name, rpc)| create_tx_sender(name, rpc, tx_config.clone(), client.clone()))
             .collect::<Vec<Arc<dyn TxSender>>>();
 
+        let landing = LandingTracker::new(
+            config.landing.clone(),
+            config.ws_rpc.clone(),
+            Arc::new(RpcClient::new(config.http_rpc.clone())),
+        );
+
         Bench {
             config,
             tx_subscribe_sender,
             rpcs,
             client,
+            landing,
         }
     }
 
+    // Every transaction of the geyser stream goes through here, our own sends included.
+    pub fn observe_signature(&self, signature: &Signature, slot: u64) {
+        self.landing.observe(signature, slot);
+    }
+
     pub async fn send_and_confirm_transaction(
         tx_index: u32,
         rpc_sender: Arc<dyn TxSender>,
         recent_blockhash: Hash,
-        swap_data: SwapData
-    ) -> anyhow::Result<()> {
+        swap_order: SwapOrder
+    ) -> anyhow::Result<TxResult> {
         let start = tokio::time::Instant::now();
 
         let tx_result = rpc_sender
             .send_transaction(
                 tx_index,
                 recent_blockhash,
-                swap_data
+                swap_order
             )
             .await?;
 
@@ -74,60 +102,153 @@ impl Bench {
             rpc_sender.name(),
             start.elapsed().as_millis() as u64
         );
-        Ok(())
+        Ok(tx_result)
     }
 
+    // `created_slot` is the slot the pool was created in, landings are reported relative to it.
     pub async fn send_swap_tx(
         self,
         recent_blockhash: Hash,
-        swap_data: SwapData
-    ) {
-        tokio::select! {
-            _ = self.send_swap_tx_inner(
-                recent_blockhash,
-                swap_data
-            ) => {}
-        }
+        swap_order: SwapOrder,
+        created_slot: Option<u64>,
+    ) -> SwapLanding {
+        self.send_swap_tx_inner(recent_blockhash, swap_order, created_slot)
+            .await
     }
 
     async fn send_swap_tx_inner(
         self,
         recent_blockhash: Hash,
-        swap_data: SwapData
-    ) {
+        swap_order: SwapOrder,
+        created_slot: Option<u64>,
+    ) -> SwapLanding {
         let start = tokio::time::Instant::now();
-        info!("starting create buy tx");
+        info!("starting create {:?} tx", swap_order.side);
+        let label = format!("{:?} on {}", swap_order.side, swap_order.target.pool());
+        // fetched alongside the sends so it doesn't delay them
+        let landing = self.landing.clone();
+        let slot_sent = tokio::spawn(async move { landing.current_slot().await });
         let mut tx_handles = Vec::new();
 
         for rpc in &self.rpcs {
-            let rpc_name = rpc.name();
             let rpc_sender = rpc.clone();
-            let client = self.client.clone();
+            let landing = self.landing.clone();
+            let swap_order = swap_order.clone();
             let hdl = tokio::spawn(async move {
                 let index = 0;
-                if let Err(e) = Self::send_and_confirm_transaction(
+                let tx_result = match Self::send_and_confirm_transaction(
                     index,
-                    rpc_sender,
+                    rpc_sender.clone(),
                     recent_blockhash,
-                    swap_data
+                    swap_order
                 )
                 .await
                 {
-                    error!("error end_and_confirm_transaction {:?}", e);
-                }
+                    Ok(tx_result) => tx_result,
+                    Err(e) => {
+                        error!("error end_and_confirm_transaction {:?}", e);
+                        return None;
+                    }
+                };
+
+                // followed as soon as this sender answers, not after the slowest one
+                let landed = landing.follow(rpc_sender.as_ref(), &tx_result).await;
+                Some(SendOutcome {
+                    rpc_name: rpc_sender.name(),
+                    tx_result,
+                    landed,
+                    elapsed_ms: start.elapsed().as_millis() as u64,
+                })
             });
             tx_handles.push(hdl);
         }
         info!("waiting for transactions to complete...");
 
-        // wait for all transactions to complete
+        tokio::spawn(self.track_landing(label, tx_handles, start, slot_sent, created_slot))
+    }
+
+    // Waits for every send's final status and reports which sender landed first.
+    async fn track_landing(
+        self,
+        label: String,
+        tx_handles: Vec<JoinHandle<Option<SendOutcome>>>,
+        start: tokio::time::Instant,
+        slot_sent: JoinHandle<Option<u64>>,
+        created_slot: Option<u64>,
+    ) -> Option<u64> {
+        let mut outcomes = Vec::new();
         for hdl in tx_handles {
-            hdl.await.unwrap_or_default();
+            if let Some(outcome) = hdl.await.unwrap_or_default() {
+                outcomes.push(outcome);
+            }
         }
 
         info!(
             "bench complete! {:?} ms",
             start.elapsed().as_millis() as u64
         );
+
+        let slot_sent = slot_sent.await.unwrap_or_default();
+        let metrics: Vec<TxMetrics> = outcomes
+            .into_iter()
+            .map(|outcome| {
+                let slot_landed = outcome.landed.map(|landed| landed.slot);
+                TxMetrics {
+                    rpc_name: outcome.rpc_name,
+                    signature: outcome.tx_result.into(),
+                    index: 0,
+                    success: outcome.landed.map_or(false, |landed| landed.success),
+                    slot_sent: slot_sent.unwrap_or_default(),
+                    slot_landed,
+                    slot_latency: slot_sent
+                        .zip(slot_landed)
+                        .map(|(sent, landed)| landed.saturating_sub(sent)),
+                    elapsed: outcome.landed.map(|_| outcome.elapsed_ms),
+                }
+            })
+            .collect();
+
+        let first = metrics
+            .iter()
+            .filter(|metrics| metrics.success)
+            .filter_map(|metrics| Some((metrics.slot_landed?, metrics)))
+            .min_by_key(|(slot, _)| *slot);
+        match first {
+            Some((slot, winner)) => {
+                let after_creation = created_slot
+                    .map(|created| {
+                        format!(", {} slots after creation", slot.saturating_sub(created))
+                    })
+                    .unwrap_or_default();
+                info!(
+                    "{}: landed by {} in slot {}{}",
+                    label, winner.rpc_name, slot, after_creation
+                );
+            }
+            None => warn!("{}: none of {} sends landed", label, metrics.len()),
+        }
+        let landed_slot = first.map(|(slot, _)| slot);
+
+        for tx_metrics in metrics {
+            let TxMetrics {
+                rpc_name,
+                signature,
+                ..
+            } = &tx_metrics;
+            match (tx_metrics.slot_landed, tx_metrics.success) {
+                (Some(slot), true) => info!(
+                    "  {}: {} landed in slot {} ({:?} slots after send, {:?} ms)",
+                    rpc_name, signature, slot, tx_metrics.slot_latency, tx_metrics.elapsed
+                ),
+                (Some(slot), false) => {
+                    info!("  {}: {} failed in slot {}", rpc_name, signature, slot)
+                }
+                (None, _) => info!("  {}: {} did not land", rpc_name, signature),
+            }
+            if self.tx_subscribe_sender.send(tx_metrics).await.is_err() {
+                warn!("tx metrics receiver dropped");
+            }
+        }
+        landed_slot
     }
 }
@@ -10,12 +10,317 @@ pub struct PingThingsArgs {
     pub ws_rpc: String,
     pub geyser_url: String,
     pub geyser_x_token: String,
+    // endpoint_name -> endpoint, raced against each other; falls back to geyser_url when empty
+    #[serde(default)]
+    pub geyser_endpoints: HashMap<String, GeyserEndpointConfig>,
+    #[serde(default)]
+    pub geyser_reconnect: GeyserReconnectConfig,
+    #[serde(default)]
+    pub websocket_source: WebsocketSourceConfig,
     pub private_key: String,
     pub compute_unit_price: u64,
     pub compute_unit_limit: u32,
     pub tip: f64,
     pub buy_amount: f64,
+    // fixed minimum out in UI units, only used for pools that can't be quoted
     pub min_amount_out: f64,
+    #[serde(default)]
+    pub quote: QuoteConfig,
+    #[serde(default)]
+    pub wsol_mode: WsolMode,
+    #[serde(default)]
+    pub pool_variants: PoolVariantsConfig,
+    #[serde(default)]
+    pub programs: ProgramsConfig,
+    #[serde(default)]
+    pub activation: ActivationConfig,
+    #[serde(default)]
+    pub positions: PositionsConfig,
+    #[serde(default)]
+    pub filters: FiltersConfig,
+    #[serde(default)]
+    pub safety: SafetyConfig,
+    #[serde(default)]
+    pub exits: ExitsConfig,
+    #[serde(default)]
+    pub landing: LandingConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GeyserEndpointConfig {
+    pub url: String,
+    #[serde(default)]
+    pub x_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GeyserReconnectConfig {
+    // first retry delay, doubled on every consecutive failure
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    // rebuild the whole grpc channel after this many failed subscribes in a row
+    pub max_failures_before_rebuild: u32,
+    pub ping_interval_secs: u64,
+    // force a reconnect when no update arrived for this long
+    pub stall_timeout_secs: u64,
+}
+
+impl Default for GeyserReconnectConfig {
+    fn default() -> Self {
+        GeyserReconnectConfig {
+            initial_backoff_ms: 250,
+            max_backoff_ms: 30_000,
+            max_failures_before_rebuild: 3,
+            ping_interval_secs: 10,
+            stall_timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WebsocketSourceConfig {
+    // subscribe to program logs over ws_rpc in addition to (or instead of) geyser
+    pub enabled: bool,
+    // only fetch transactions whose logs contain one of these substrings
+    pub log_filters: Vec<String>,
+}
+
+impl Default for WebsocketSourceConfig {
+    fn default() -> Self {
+        WebsocketSourceConfig {
+            enabled: false,
+            log_filters: vec!["Instruction: Initialize".to_string()],
+        }
+    }
+}
+
+// which dynamic AMM pool-creation instructions trigger a buy
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PoolVariantsConfig {
+    pub permissionless: bool,
+    pub permissionless_with_fee_tier: bool,
+    pub with_config: bool,
+    pub with_config2: bool,
+    pub customizable: bool,
+}
+
+impl Default for PoolVariantsConfig {
+    fn default() -> Self {
+        PoolVariantsConfig {
+            permissionless: true,
+            permissionless_with_fee_tier: true,
+            with_config: true,
+            with_config2: true,
+            customizable: true,
+        }
+    }
+}
+
+// which meteora programs are watched for new pools
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProgramsConfig {
+    pub dynamic_amm: bool,
+    pub dlmm: bool,
+    pub damm_v2: bool,
+    // dynamic bonding curve migrations into dynamic AMM / DAMM v2 pools
+    pub dbc: bool,
+}
+
+impl Default for ProgramsConfig {
+    fn default() -> Self {
+        ProgramsConfig {
+            dynamic_amm: true,
+            dlmm: false,
+            damm_v2: false,
+            dbc: false,
+        }
+    }
+}
+
+// timing of the first buy into pools that activate in the future
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ActivationConfig {
+    // send this long before the activation slot / time to cover propagation
+    pub lead_time_ms: u64,
+    // pools activating further out than this are skipped
+    pub max_wait_secs: u64,
+    // used to turn a slot distance into a wait time
+    pub slot_duration_ms: u64,
+}
+
+impl Default for ActivationConfig {
+    fn default() -> Self {
+        ActivationConfig {
+            lead_time_ms: 400,
+            max_wait_secs: 900,
+            slot_duration_ms: 400,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PositionsConfig {
+    // new pools are skipped while this many positions are open
+    pub max_open_positions: usize,
+    // dynamic AMM pools we already hold, e.g. bought before a restart; tracked for exits
+    pub existing_pools: Vec<String>,
+}
+
+impl Default for PositionsConfig {
+    fn default() -> Self {
+        PositionsConfig {
+            max_open_positions: 3,
+            existing_pools: Vec::new(),
+        }
+    }
+}
+
+// rules a new pool must pass before it is bought, empty lists don't filter
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FiltersConfig {
+    // initial WSOL deposit bounds, in SOL
+    pub min_sol_liquidity: Option<f64>,
+    pub max_sol_liquidity: Option<f64>,
+    // reject pools whose creation doesn't carry the deposit (DLMM, DAMM v2)
+    pub require_known_liquidity: bool,
+    pub mint_allowlist: Vec<String>,
+    pub mint_denylist: Vec<String>,
+    pub creator_allowlist: Vec<String>,
+    pub creator_denylist: Vec<String>,
+    // only buy pools created with one of these config accounts (fee tiers)
+    pub pool_configs: Vec<String>,
+    // reject pools activating more than this many slots after we see them
+    pub max_activation_slots: Option<u64>,
+}
+
+// on-chain checks of the mint we are about to buy
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SafetyConfig {
+    pub enabled: bool,
+    pub reject_mint_authority: bool,
+    pub reject_freeze_authority: bool,
+    // token-2022 extensions
+    pub reject_transfer_fee: bool,
+    pub reject_transfer_hook: bool,
+    pub reject_permanent_delegate: bool,
+    pub reject_non_transferable: bool,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        SafetyConfig {
+            enabled: true,
+            reject_mint_authority: true,
+            reject_freeze_authority: true,
+            reject_transfer_fee: true,
+            reject_transfer_hook: true,
+            reject_permanent_delegate: true,
+            reject_non_transferable: true,
+        }
+    }
+}
+
+// expected output of the buy, computed from the pool's initial reserves
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QuoteConfig {
+    // min out = expected out minus this much
+    pub slippage_bps: u64,
+    // skip the buy when it moves the price more than this
+    pub max_price_impact_bps: Option<u64>,
+    // fee assumed for sells on pools whose fee isn't known, such buys use `min_amount_out`
+    pub default_trade_fee_bps: u64,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        QuoteConfig {
+            slippage_bps: 500,
+            max_price_impact_bps: None,
+            default_trade_fee_bps: 25,
+        }
+    }
+}
+
+// when to sell an open position, every rule is optional
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ExitsConfig {
+    pub enabled: bool,
+    // sell once the price reaches this multiple of the entry price, e.g. 2.0
+    pub take_profit_multiple: Option<f64>,
+    // sell once the price is this many percent below the entry price
+    pub stop_loss_pct: Option<f64>,
+    // sell once the price is this many percent below the highest price seen
+    pub trailing_stop_pct: Option<f64>,
+    // sell whatever the price once the position is this old
+    pub max_hold_secs: Option<u64>,
+    pub sell_slippage_bps: u64,
+    // how often positions are re-checked without a price update (max hold)
+    pub check_interval_ms: u64,
+}
+
+impl Default for ExitsConfig {
+    fn default() -> Self {
+        ExitsConfig {
+            enabled: false,
+            take_profit_multiple: None,
+            stop_loss_pct: None,
+            trailing_stop_pct: None,
+            max_hold_secs: None,
+            sell_slippage_bps: 1000,
+            check_interval_ms: 1000,
+        }
+    }
+}
+
+// how long and how often sent transactions are followed until they land
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LandingConfig {
+    // a send that hasn't landed by then is reported as dropped
+    pub timeout_secs: u64,
+    // getSignatureStatuses polling, alongside the geyser stream and a ws_rpc subscription
+    pub poll_interval_ms: u64,
+    // the block engine answers Invalid until it has indexed a fresh bundle
+    pub invalid_bundle_grace_ms: u64,
+}
+
+impl Default for LandingConfig {
+    fn default() -> Self {
+        LandingConfig {
+            timeout_secs: 60,
+            poll_interval_ms: 500,
+            invalid_bundle_grace_ms: 5000,
+        }
+    }
+}
+
+// how the buy gets its WSOL
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WsolMode {
+    // the WSOL account already holds enough, fewest instructions
+    #[default]
+    Prefunded,
+    // create and fund the WSOL account in the buy transaction
+    Wrap,
+    // like wrap, and close the WSOL account after the swap to get leftovers back
+    WrapAndClose,
+}
+
+impl WsolMode {
+    pub fn wraps(&self) -> bool {
+        matches!(self, WsolMode::Wrap | WsolMode::WrapAndClose)
+    }
 }
 
 #[derive(Debug, Deserialize, Default, Clone)]
@@ -26,6 +331,8 @@ pub enum RpcType {
     Jito,
     Bloxroute,
     Nextblock,
+    // QUIC straight to the TPU of the upcoming leaders, url serves the leader schedule
+    Tpu,
 }
 #[derive(Clone, Debug, Deserialize)]
 pub struct RpcConfig {
@@ -34,6 +341,58 @@ pub struct RpcConfig {
     pub auth: Option<String>,
     #[serde(default)]
     pub rpc_type: RpcType,
+    // jito only, where bundle statuses and tip accounts are fetched; derived from url when unset
+    #[serde(default)]
+    pub bundle_url: Option<String>,
+    #[serde(default)]
+    pub jito_mode: JitoMode,
+    // jito only, how transactions are encoded in the request
+    #[serde(default)]
+    pub encoding: TxEncoding,
+    #[serde(default)]
+    pub tpu: TpuConfig,
+}
+
+// tpu only
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TpuConfig {
+    // the current leader and this many after it get the transaction
+    pub next_leaders: u64,
+    // slot updates; derived from the rpc url (http -> ws, port + 1) when unset
+    pub ws_url: Option<String>,
+    // base58 keypair of a staked validator identity, QUIC connections signed with it get
+    // stake-weighted QoS; an unstaked throwaway identity is used when unset
+    pub identity: Option<String>,
+}
+
+impl Default for TpuConfig {
+    fn default() -> Self {
+        TpuConfig {
+            next_leaders: 2,
+            ws_url: None,
+            identity: None,
+        }
+    }
+}
+
+// how a jito rpc entry submits the swap
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JitoMode {
+    // sendTransaction, the tip is an instruction of the swap transaction
+    #[default]
+    Transaction,
+    // sendBundle of the swap and a separate tip transaction, they land together or not at all
+    Bundle,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TxEncoding {
+    #[default]
+    Base58,
+    Base64,
 }
 
 impl PingThingsArgs {
@@ -41,4 +400,22 @@ impl PingThingsArgs {
         let config_yaml = fs::read_to_string("./config.yaml").expect("cannot find config file");
         serde_yaml::from_str::<PingThingsArgs>(&config_yaml).expect("invalid config file")
     }
+
+    pub fn geyser_endpoints(&self) -> HashMap<String, GeyserEndpointConfig> {
+        if !self.geyser_endpoints.is_empty() {
+            return self.geyser_endpoints.clone();
+        }
+
+        let mut endpoints = HashMap::new();
+        if !self.geyser_url.is_empty() {
+            endpoints.insert(
+                "geyser".to_string(),
+                GeyserEndpointConfig {
+                    url: self.geyser_url.clone(),
+                    x_token: Some(self.geyser_x_token.clone()),
+                },
+            );
+        }
+        endpoints
+    }
 }
@@ -0,0 +1,217 @@
+use borsh::BorshDeserialize;
+use std::io::ErrorKind;
+use thiserror::Error;
+
+// Anchor instruction discriminators are the first 8 bytes of sha256("global:<instruction_name>")
+pub const IX_DISCRIMINATOR_SIZE: usize = 8;
+// Anchor account discriminators are the first 8 bytes of sha256("account:<AccountName>")
+pub const ACCOUNT_DISCRIMINATOR_SIZE: usize = 8;
+
+#[derive(Error, Debug)]
+pub enum DecodeError {
+    #[error("instruction data is {0} bytes, too short for a discriminator")]
+    MissingDiscriminator(usize),
+    #[error("unknown instruction discriminator {0:?}")]
+    UnknownDiscriminator([u8; 8]),
+    #[error("{0}: instruction data is truncated")]
+    TruncatedData(&'static str),
+    #[error("{0}: invalid instruction data: {1}")]
+    InvalidData(&'static str, String),
+    #[error("{0}: account discriminator mismatch")]
+    WrongAccountType(&'static str),
+    #[error("{instruction}: expected {expected} accounts, got {actual}")]
+    NotEnoughAccounts {
+        instruction: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+pub fn split_discriminator(data: &[u8]) -> Result<([u8; 8], &[u8]), DecodeError> {
+    let discriminator: [u8; 8] = data
+        .get(..IX_DISCRIMINATOR_SIZE)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(DecodeError::MissingDiscriminator(data.len()))?;
+    Ok((discriminator, &data[IX_DISCRIMINATOR_SIZE..]))
+}
+
+// zeros appended to data that failed to decode, to tell truncated data from invalid data
+const TRUNCATION_PADDING: usize = 16 * 1024;
+
+// Borsh-decodes instruction arguments. Trailing bytes are ignored, so args structs
+// may describe only a prefix of the on-chain layout.
+pub fn args<T: BorshDeserialize>(instruction: &'static str, data: &[u8]) -> Result<T, DecodeError> {
+    T::deserialize(&mut &data[..]).map_err(|err| {
+        // borsh reports running out of input as InvalidData too: the data is truncated when
+        // it decodes once more bytes follow
+        let mut padded = data.to_vec();
+        padded.resize(data.len() + TRUNCATION_PADDING, 0);
+        let truncated = T::deserialize(&mut padded.as_slice()).is_ok();
+        if err.kind() == ErrorKind::UnexpectedEof || truncated {
+            DecodeError::TruncatedData(instruction)
+        } else {
+            DecodeError::InvalidData(instruction, err.to_string())
+        }
+    })
+}
+
+// Borsh-decodes an anchor account after checking its discriminator.
+pub fn account<T: BorshDeserialize>(
+    name: &'static str,
+    discriminator: [u8; 8],
+    data: &[u8],
+) -> Result<T, DecodeError> {
+    if data.get(..ACCOUNT_DISCRIMINATOR_SIZE) != Some(discriminator.as_slice()) {
+        return Err(DecodeError::WrongAccountType(name));
+    }
+    args(name, &data[ACCOUNT_DISCRIMINATOR_SIZE..])
+}
+
+// Declares a struct with one named pubkey per account of an instruction, in IDL order.
+macro_rules! instruction_accounts {
+    ($name:ident { $($field:ident),+ $(,)? }) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name {
+            $(pub $field: ::solana_sdk::pubkey::Pubkey,)+
+        }
+
+        impl $name {
+            pub const LEN: usize = [$(stringify!($field)),+].len();
+
+            pub fn from_accounts(
+                instruction: &'static str,
+                accounts: &[::solana_sdk::instruction::AccountMeta],
+            ) -> Result<Self, $crate::core::decode::DecodeError> {
+                if accounts.len() < Self::LEN {
+                    return Err($crate::core::decode::DecodeError::NotEnoughAccounts {
+                        instruction,
+                        expected: Self::LEN,
+                        actual: accounts.len(),
+                    });
+                }
+                let mut accounts = accounts.iter().map(|account| account.pubkey);
+                Ok($name {
+                    $($field: accounts.next().unwrap_or_default(),)+
+                })
+            }
+        }
+    };
+}
+
+pub(crate) use instruction_accounts;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::AccountMeta;
+    use solana_sdk::pubkey::Pubkey;
+
+    const DISCRIMINATOR: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    #[derive(Debug, PartialEq, BorshDeserialize)]
+    struct Args {
+        amount: u64,
+        enabled: bool,
+    }
+
+    instruction_accounts!(TestAccounts { pool, user, token_program });
+
+    #[test]
+    fn discriminator() {
+        let (discriminator, data) = split_discriminator(&[1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap();
+        assert_eq!(discriminator, DISCRIMINATOR);
+        assert_eq!(data, [9]);
+
+        assert!(split_discriminator(&DISCRIMINATOR).unwrap().1.is_empty());
+        assert!(matches!(
+            split_discriminator(&[1, 2, 3]),
+            Err(DecodeError::MissingDiscriminator(3))
+        ));
+    }
+
+    #[test]
+    fn args_ignore_trailing_bytes() {
+        let data = [7, 0, 0, 0, 0, 0, 0, 0, 1, 0xff, 0xff];
+        assert_eq!(
+            args::<Args>("test", &data).unwrap(),
+            Args {
+                amount: 7,
+                enabled: true
+            }
+        );
+    }
+
+    #[test]
+    fn truncated_args() {
+        for len in 0..9 {
+            assert!(
+                matches!(
+                    args::<Args>("test", &[0; 9][..len]),
+                    Err(DecodeError::TruncatedData("test"))
+                ),
+                "{} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_args() {
+        let data = [7, 0, 0, 0, 0, 0, 0, 0, 2];
+        assert!(matches!(
+            args::<Args>("test", &data),
+            Err(DecodeError::InvalidData("test", _))
+        ));
+    }
+
+    #[test]
+    fn account_discriminator() {
+        let mut data = DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[7, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            account::<Args>("Test", DISCRIMINATOR, &data).unwrap(),
+            Args {
+                amount: 7,
+                enabled: false
+            }
+        );
+
+        assert!(matches!(
+            account::<Args>("Test", [0; 8], &data),
+            Err(DecodeError::WrongAccountType("Test"))
+        ));
+        assert!(matches!(
+            account::<Args>("Test", DISCRIMINATOR, &data[..4]),
+            Err(DecodeError::WrongAccountType("Test"))
+        ));
+        assert!(matches!(
+            account::<Args>("Test", DISCRIMINATOR, &data[..12]),
+            Err(DecodeError::TruncatedData("Test"))
+        ));
+    }
+
+    #[test]
+    fn accounts_in_order() {
+        let metas: Vec<AccountMeta> = (0..4)
+            .map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+            .collect();
+        let accounts = TestAccounts::from_accounts("test", &metas).unwrap();
+        assert_eq!(TestAccounts::LEN, 3);
+        assert_eq!(accounts.pool, metas[0].pubkey);
+        assert_eq!(accounts.user, metas[1].pubkey);
+        assert_eq!(accounts.token_program, metas[2].pubkey);
+    }
+
+    #[test]
+    fn not_enough_accounts() {
+        let metas = [AccountMeta::new_readonly(Pubkey::new_unique(), false)];
+        assert!(matches!(
+            TestAccounts::from_accounts("test", &metas),
+            Err(DecodeError::NotEnoughAccounts {
+                instruction: "test",
+                expected: 3,
+                actual: 1,
+            })
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-use solana_sdk::instruction::AccountMeta;
+use solana_sdk::instruction::{AccountMeta, CompiledInstruction, Instruction};
 use solana_sdk::message::v0::{LoadedAddresses, LoadedMessage};
 use solana_sdk::message::VersionedMessage;
 use solana_sdk::pubkey::Pubkey;
@@ -7,95 +7,131 @@ use solana_sdk::transaction::VersionedTransaction;
 use solana_transaction_status::TransactionStatusMeta;
 use crate::geyser::GeyserResult;
 
-pub fn extract_instructions(
-    meta_data: TransactionStatusMeta,
-    transaction: VersionedTransaction
-) -> GeyserResult<Vec<solana_sdk::instruction::Instruction>> {
-    let message = transaction.message.clone();
-    let meta = meta_data.clone();
-
-    let mut instructions =
-        Vec::<solana_sdk::instruction::Instruction>::new();
-
-    match message {
-        VersionedMessage::Legacy(legacy) => {
-            for (i, compiled_instruction) in legacy.instructions.iter().enumerate() {
-                let program_id = *legacy
-                    .account_keys
-                    .get(compiled_instruction.program_id_index as usize)
-                    .unwrap_or(&Pubkey::default());
-
-                let accounts: Vec<_> = compiled_instruction
-                    .accounts
-                    .iter()
-                    .filter_map(|account_index| {
-                        let account_pubkey = legacy.account_keys.get(*account_index as usize)?;
-                        Some(AccountMeta {
-                            pubkey: *account_pubkey,
-                            is_writable: legacy.is_maybe_writable(*account_index as usize, None),
-                            is_signer: legacy.is_signer(*account_index as usize),
-                        })
-                    })
+pub mod decode;
+
+#[derive(Debug, Clone)]
+pub struct ExtractedInstruction {
+    pub instruction: Instruction,
+    // index of the top-level instruction this one was executed under
+    pub outer_index: usize,
+    // position in the outer instruction's inner instruction list, None for top-level instructions
+    pub inner_index: Option<usize>,
+    // 1 for top-level instructions, 2+ for CPIs
+    pub stack_height: u32,
+}
+
+impl ExtractedInstruction {
+    pub fn is_cpi(&self) -> bool {
+        self.inner_index.is_some()
+    }
+}
+
+// Resolved account keys of a message with their signer / writable flags,
+// including addresses loaded from lookup tables for v0 messages.
+struct ResolvedAccounts {
+    keys: Vec<Pubkey>,
+    writable: Vec<bool>,
+    signer: Vec<bool>,
+}
+
+impl ResolvedAccounts {
+    fn new(message: &VersionedMessage, meta: &TransactionStatusMeta) -> Self {
+        match message {
+            VersionedMessage::Legacy(legacy) => {
+                let keys = legacy.account_keys.clone();
+                let writable = (0..keys.len())
+                    .map(|index| legacy.is_maybe_writable(index, None))
                     .collect();
+                let signer = (0..keys.len()).map(|index| legacy.is_signer(index)).collect();
+                ResolvedAccounts { keys, writable, signer }
+            }
+            VersionedMessage::V0(v0) => {
+                let loaded_addresses = LoadedAddresses {
+                    writable: meta.loaded_addresses.writable.clone(),
+                    readonly: meta.loaded_addresses.readonly.clone(),
+                };
 
-                instructions.push(solana_sdk::instruction::Instruction {
-                    program_id,
-                    accounts,
-                    data: compiled_instruction.data.clone(),
-                });
+                let loaded_message = LoadedMessage::new(
+                    v0.clone(),
+                    loaded_addresses,
+                    &ReservedAccountKeys::empty_key_set(),
+                );
+
+                let keys: Vec<Pubkey> = loaded_message.account_keys().iter().cloned().collect();
+                let writable = (0..keys.len())
+                    .map(|index| loaded_message.is_writable(index))
+                    .collect();
+                let signer = (0..keys.len())
+                    .map(|index| loaded_message.is_signer(index))
+                    .collect();
+                ResolvedAccounts { keys, writable, signer }
             }
         }
-        VersionedMessage::V0(v0) => {
-            let loaded_addresses = LoadedAddresses {
-                writable: meta
-                    .loaded_addresses
-                    .writable
-                    .iter()
-                    .map(|key| key.clone())
-                    .collect(),
-                readonly: meta
-                    .loaded_addresses
-                    .readonly
-                    .iter()
-                    .map(|key| key.clone())
-                    .collect(),
-            };
-
-            let loaded_message = LoadedMessage::new(
-                v0.clone(),
-                loaded_addresses,
-                &ReservedAccountKeys::empty_key_set(),
-            );
-
-            for (i, compiled_instruction) in v0.instructions.iter().enumerate() {
-                let program_id = *loaded_message
-                    .account_keys()
-                    .get(compiled_instruction.program_id_index as usize)
-                    .unwrap_or(&Pubkey::default());
-
-                let accounts: Vec<AccountMeta> = compiled_instruction
-                    .accounts
-                    .iter()
-                    .filter_map(|account_index| {
-                        let account_pubkey =
-                            loaded_message.account_keys().get(*account_index as usize);
-
-                        return Some(AccountMeta {
-                            pubkey: account_pubkey.map(|acc| acc.clone()).unwrap_or_default(),
-                            is_writable: loaded_message.is_writable(*account_index as usize),
-                            is_signer: loaded_message.is_signer(*account_index as usize),
-                        });
-                    })
-                    .collect();
+    }
 
-                instructions.push(solana_sdk::instruction::Instruction {
-                    program_id,
-                    accounts,
-                    data: compiled_instruction.data.clone(),
-                });
-            }
+    fn instruction(&self, compiled_instruction: &CompiledInstruction) -> Instruction {
+        let program_id = *self
+            .keys
+            .get(compiled_instruction.program_id_index as usize)
+            .unwrap_or(&Pubkey::default());
+
+        let accounts: Vec<AccountMeta> = compiled_instruction
+            .accounts
+            .iter()
+            .map(|account_index| {
+                let index = *account_index as usize;
+                AccountMeta {
+                    pubkey: self.keys.get(index).cloned().unwrap_or_default(),
+                    is_writable: self.writable.get(index).cloned().unwrap_or_default(),
+                    is_signer: self.signer.get(index).cloned().unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Instruction {
+            program_id,
+            accounts,
+            data: compiled_instruction.data.clone(),
+        }
+    }
+}
+
+// Returns every instruction of the transaction in execution order: each top-level
+// instruction followed by the instructions it invoked through CPI.
+pub fn extract_instructions(
+    meta_data: TransactionStatusMeta,
+    transaction: VersionedTransaction
+) -> GeyserResult<Vec<ExtractedInstruction>> {
+    let message = &transaction.message;
+    let accounts = ResolvedAccounts::new(message, &meta_data);
+
+    let mut instructions = Vec::<ExtractedInstruction>::new();
+
+    for (outer_index, compiled_instruction) in message.instructions().iter().enumerate() {
+        instructions.push(ExtractedInstruction {
+            instruction: accounts.instruction(compiled_instruction),
+            outer_index,
+            inner_index: None,
+            stack_height: 1,
+        });
+
+        let inner_instructions = meta_data
+            .inner_instructions
+            .iter()
+            .flatten()
+            .filter(|inner| inner.index as usize == outer_index)
+            .flat_map(|inner| inner.instructions.iter());
+
+        for (inner_index, inner_instruction) in inner_instructions.enumerate() {
+            instructions.push(ExtractedInstruction {
+                instruction: accounts.instruction(&inner_instruction.instruction),
+                outer_index,
+                inner_index: Some(inner_index),
+                // nodes that predate stack height tracking report None, those are at least one level deep
+                stack_height: inner_instruction.stack_height.unwrap_or(2),
+            });
         }
     }
 
     Ok(instructions)
-}
\ No newline at end of file
+}
@@ -0,0 +1,301 @@
+use crate::activation::{ActivationInfo, ActivationType};
+use crate::quote::{PoolReserves, TradeFee};
+use crate::core::decode::{args, instruction_accounts, split_discriminator, DecodeError};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+pub const DAMM_V2_PROGRAM_ID: Pubkey = pubkey!("cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG");
+
+pub const INITIALIZE_POOL_DISC: [u8; 8] = [95, 180, 10, 172, 84, 174, 232, 40];
+pub const INITIALIZE_POOL_WITH_DYNAMIC_CONFIG_DISC: [u8; 8] = [149, 82, 72, 197, 253, 252, 68, 15];
+pub const INITIALIZE_CUSTOMIZABLE_POOL_DISC: [u8; 8] = [20, 161, 241, 24, 189, 221, 180, 2];
+pub const SWAP_DISC: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+
+instruction_accounts!(InitializePoolAccounts {
+    creator, position_nft_mint, position_nft_account, payer, config, pool_authority, pool,
+    position, token_a_mint, token_b_mint, token_a_vault, token_b_vault, payer_token_a,
+    payer_token_b, token_a_program, token_b_program, token_2022_program, system_program,
+    event_authority, program,
+});
+
+instruction_accounts!(InitializePoolWithDynamicConfigAccounts {
+    creator, position_nft_mint, position_nft_account, payer, pool_creator_authority, config,
+    pool_authority, pool, position, token_a_mint, token_b_mint, token_a_vault, token_b_vault,
+    payer_token_a, payer_token_b, token_a_program, token_b_program, token_2022_program,
+    system_program, event_authority, program,
+});
+
+instruction_accounts!(InitializeCustomizablePoolAccounts {
+    creator, position_nft_mint, position_nft_account, payer, pool_authority, pool, position,
+    token_a_mint, token_b_mint, token_a_vault, token_b_vault, payer_token_a, payer_token_b,
+    token_a_program, token_b_program, token_2022_program, system_program, event_authority,
+    program,
+});
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct InitializePoolParameters {
+    pub liquidity: u128,
+    pub sqrt_price: u128,
+    pub activation_point: Option<u64>,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct BaseFeeParameters {
+    pub cliff_fee_numerator: u64,
+    pub number_of_period: u16,
+    pub period_frequency: u64,
+    pub reduction_factor: u64,
+    pub fee_scheduler_mode: u8,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct DynamicFeeParameters {
+    pub bin_step: u16,
+    pub bin_step_u128: u128,
+    pub filter_period: u16,
+    pub decay_period: u16,
+    pub reduction_factor: u16,
+    pub max_volatility_accumulator: u32,
+    pub variable_fee_control: u32,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct PoolFeeParameters {
+    pub base_fee: BaseFeeParameters,
+    pub padding: [u8; 3],
+    pub dynamic_fee: Option<DynamicFeeParameters>,
+}
+
+// fees are expressed over 1e9
+pub const FEE_DENOMINATOR: u64 = 1_000_000_000;
+
+impl PoolFeeParameters {
+    // the base fee at activation; the fee scheduler only lowers it from there
+    pub fn trade_fee(&self) -> TradeFee {
+        TradeFee {
+            numerator: self.base_fee.cliff_fee_numerator,
+            denominator: FEE_DENOMINATOR,
+        }
+    }
+}
+
+// shared by initialize_customizable_pool and initialize_pool_with_dynamic_config
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct InitializeCustomizablePoolParameters {
+    pub pool_fees: PoolFeeParameters,
+    pub sqrt_min_price: u128,
+    pub sqrt_max_price: u128,
+    pub has_alpha_vault: bool,
+    pub liquidity: u128,
+    pub sqrt_price: u128,
+    // 0 = slot, 1 = timestamp
+    pub activation_type: u8,
+    pub collect_fee_mode: u8,
+    pub activation_point: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub enum DammV2Instruction {
+    InitializePool {
+        accounts: InitializePoolAccounts,
+        args: InitializePoolParameters,
+    },
+    InitializePoolWithDynamicConfig {
+        accounts: InitializePoolWithDynamicConfigAccounts,
+        args: InitializeCustomizablePoolParameters,
+    },
+    InitializeCustomizablePool {
+        accounts: InitializeCustomizablePoolAccounts,
+        args: InitializeCustomizablePoolParameters,
+    },
+}
+
+impl DammV2Instruction {
+    // Decodes the DAMM v2 pool-creation instructions, everything else is reported as unknown.
+    pub fn decode(instruction: &Instruction) -> Result<Self, DecodeError> {
+        let accounts = instruction.accounts.as_slice();
+        let (discriminator, data) = split_discriminator(&instruction.data)?;
+
+        let decoded = match discriminator {
+            INITIALIZE_POOL_DISC => {
+                let name = "initialize_pool";
+                DammV2Instruction::InitializePool {
+                    accounts: InitializePoolAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            INITIALIZE_POOL_WITH_DYNAMIC_CONFIG_DISC => {
+                let name = "initialize_pool_with_dynamic_config";
+                DammV2Instruction::InitializePoolWithDynamicConfig {
+                    accounts: InitializePoolWithDynamicConfigAccounts::from_accounts(
+                        name, accounts,
+                    )?,
+                    args: args(name, data)?,
+                }
+            }
+            INITIALIZE_CUSTOMIZABLE_POOL_DISC => {
+                let name = "initialize_customizable_pool";
+                DammV2Instruction::InitializeCustomizablePool {
+                    accounts: InitializeCustomizablePoolAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            unknown => return Err(DecodeError::UnknownDiscriminator(unknown)),
+        };
+
+        Ok(decoded)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DammV2Instruction::InitializePool { .. } => "initialize_pool",
+            DammV2Instruction::InitializePoolWithDynamicConfig { .. } => {
+                "initialize_pool_with_dynamic_config"
+            }
+            DammV2Instruction::InitializeCustomizablePool { .. } => "initialize_customizable_pool",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DammV2SwapData {
+    pub pool: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub token_a_vault: Pubkey,
+    pub token_b_vault: Pubkey,
+    pub token_a_program: Pubkey,
+    pub token_b_program: Pubkey,
+}
+
+impl DammV2SwapData {
+    pub fn swap_instruction(
+        &self,
+        user: Pubkey,
+        user_token_in: Pubkey,
+        user_token_out: Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Instruction {
+        let mut data = Vec::with_capacity(24);
+        data.extend_from_slice(&SWAP_DISC);
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+        let accounts = vec![
+            AccountMeta::new_readonly(pool_authority(), false),
+            AccountMeta::new(self.pool, false),
+            AccountMeta::new(user_token_in, false),
+            AccountMeta::new(user_token_out, false),
+            AccountMeta::new(self.token_a_vault, false),
+            AccountMeta::new(self.token_b_vault, false),
+            AccountMeta::new_readonly(self.token_a_mint, false),
+            AccountMeta::new_readonly(self.token_b_mint, false),
+            AccountMeta::new_readonly(user, true),
+            AccountMeta::new_readonly(self.token_a_program, false),
+            AccountMeta::new_readonly(self.token_b_program, false),
+            // anchor encodes a missing optional account as the program id
+            AccountMeta::new_readonly(DAMM_V2_PROGRAM_ID, false), // referral_token_account (none)
+            AccountMeta::new_readonly(event_authority(), false),
+            AccountMeta::new_readonly(DAMM_V2_PROGRAM_ID, false),
+        ];
+
+        Instruction {
+            program_id: DAMM_V2_PROGRAM_ID,
+            accounts,
+            data,
+        }
+    }
+}
+
+// A new DAMM v2 pool seen in the stream.
+#[derive(Debug, Clone)]
+pub struct DammV2PoolCreation {
+    pub swap_data: DammV2SwapData,
+    pub liquidity: u128,
+    pub sqrt_price: u128,
+    pub activation_point: Option<u64>,
+    pub activation_type: Option<ActivationType>,
+    // unknown for config pools, the fee lives in the config account
+    pub trade_fee: Option<TradeFee>,
+    // pool config account, not set for customizable pools
+    pub config: Option<Pubkey>,
+    pub creator: Pubkey,
+}
+
+// every creation variant carries the same pool, mint, vault and token program accounts
+macro_rules! damm_v2_pool_creation {
+    ($accounts:expr, $args:expr, $activation_type:expr, $trade_fee:expr, $config:expr) => {
+        DammV2PoolCreation {
+            swap_data: DammV2SwapData {
+                pool: $accounts.pool,
+                token_a_mint: $accounts.token_a_mint,
+                token_b_mint: $accounts.token_b_mint,
+                token_a_vault: $accounts.token_a_vault,
+                token_b_vault: $accounts.token_b_vault,
+                token_a_program: $accounts.token_a_program,
+                token_b_program: $accounts.token_b_program,
+            },
+            liquidity: $args.liquidity,
+            sqrt_price: $args.sqrt_price,
+            activation_point: $args.activation_point,
+            activation_type: $activation_type,
+            trade_fee: $trade_fee,
+            config: $config,
+            creator: $accounts.creator,
+        }
+    };
+}
+
+impl DammV2PoolCreation {
+    pub fn from_instruction(instruction: DammV2Instruction) -> Self {
+        match instruction {
+            // the activation type lives in the config account
+            DammV2Instruction::InitializePool { accounts, args } => {
+                damm_v2_pool_creation!(accounts, args, None, None, Some(accounts.config))
+            }
+            DammV2Instruction::InitializePoolWithDynamicConfig { accounts, args } => {
+                let activation_type = ActivationType::from_u8(args.activation_type);
+                let trade_fee = Some(args.pool_fees.trade_fee());
+                damm_v2_pool_creation!(
+                    accounts,
+                    args,
+                    activation_type,
+                    trade_fee,
+                    Some(accounts.config)
+                )
+            }
+            DammV2Instruction::InitializeCustomizablePool { accounts, args } => {
+                let activation_type = ActivationType::from_u8(args.activation_type);
+                let trade_fee = Some(args.pool_fees.trade_fee());
+                damm_v2_pool_creation!(accounts, args, activation_type, trade_fee, None)
+            }
+        }
+    }
+
+    pub fn reserves(&self) -> PoolReserves {
+        PoolReserves::Concentrated {
+            liquidity: self.liquidity,
+            sqrt_price: self.sqrt_price,
+        }
+    }
+
+    pub fn activation(&self) -> ActivationInfo {
+        ActivationInfo {
+            point: self.activation_point,
+            activation_type: self.activation_type,
+            // DAMM v2 configs have their own layout, the dynamic AMM decoder doesn't apply
+            config: None,
+        }
+    }
+}
+
+pub fn pool_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"pool_authority"], &DAMM_V2_PROGRAM_ID).0
+}
+
+pub fn event_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority"], &DAMM_V2_PROGRAM_ID).0
+}
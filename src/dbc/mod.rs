@@ -0,0 +1,187 @@
+use crate::core::decode::{instruction_accounts, split_discriminator, DecodeError};
+use crate::damm_v2::{DammV2Instruction, DammV2SwapData, DAMM_V2_PROGRAM_ID};
+use crate::meteora::instructions::MeteoraInstruction;
+use crate::meteora::{SwapData, SwapTarget};
+use crate::quote::{PoolReserves, TradeFee};
+use crate::METEORA_PROGRAM_ID;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+pub const DBC_PROGRAM_ID: Pubkey = pubkey!("dbcij3LWUppWqq96dh6gJWwBifmcGfLSB5D4DuSMaqN");
+
+pub const MIGRATE_METEORA_DAMM_DISC: [u8; 8] = [27, 1, 48, 22, 180, 63, 118, 217];
+pub const MIGRATION_DAMM_V2_DISC: [u8; 8] = [156, 169, 230, 103, 53, 228, 80, 64];
+
+// migration into a dynamic AMM v1 pool
+instruction_accounts!(MigrateMeteoraDammAccounts {
+    virtual_pool, migration_metadata, config, pool_authority, pool, damm_config, lp_mint,
+    token_a_mint, token_b_mint, a_vault, b_vault, a_token_vault, b_token_vault, a_vault_lp_mint,
+    b_vault_lp_mint, a_vault_lp, b_vault_lp, base_vault, quote_vault, virtual_pool_lp,
+    protocol_token_a_fee, protocol_token_b_fee, payer, rent, mint_metadata, metadata_program,
+    amm_program, vault_program, token_program, associated_token_program, system_program,
+});
+
+// migration into a DAMM v2 pool
+instruction_accounts!(MigrationDammV2Accounts {
+    virtual_pool, migration_metadata, config, pool_authority, pool, first_position_nft_mint,
+    first_position_nft_account, first_position, second_position_nft_mint,
+    second_position_nft_account, second_position, damm_pool_authority, amm_program, base_mint,
+    quote_mint, token_a_vault, token_b_vault, base_vault, quote_vault, payer, token_base_program,
+    token_quote_program, token_2022_program, damm_event_authority, system_program,
+});
+
+// Both migrations take no arguments, everything they move is in the accounts and the
+// pool-creation CPI they issue.
+#[derive(Debug, Clone)]
+pub enum DbcInstruction {
+    MigrateMeteoraDamm { accounts: MigrateMeteoraDammAccounts },
+    MigrationDammV2 { accounts: MigrationDammV2Accounts },
+}
+
+impl DbcInstruction {
+    // Decodes the DBC migration instructions, everything else is reported as unknown.
+    pub fn decode(instruction: &Instruction) -> Result<Self, DecodeError> {
+        let accounts = instruction.accounts.as_slice();
+        let (discriminator, _) = split_discriminator(&instruction.data)?;
+
+        let decoded = match discriminator {
+            MIGRATE_METEORA_DAMM_DISC => DbcInstruction::MigrateMeteoraDamm {
+                accounts: MigrateMeteoraDammAccounts::from_accounts("migrate_meteora_damm", accounts)?,
+            },
+            MIGRATION_DAMM_V2_DISC => DbcInstruction::MigrationDammV2 {
+                accounts: MigrationDammV2Accounts::from_accounts("migration_damm_v2", accounts)?,
+            },
+            unknown => return Err(DecodeError::UnknownDiscriminator(unknown)),
+        };
+
+        Ok(decoded)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DbcInstruction::MigrateMeteoraDamm { .. } => "migrate_meteora_damm",
+            DbcInstruction::MigrationDammV2 { .. } => "migration_damm_v2",
+        }
+    }
+}
+
+// A bonding curve that just graduated into a tradable pool.
+#[derive(Debug, Clone)]
+pub struct DbcMigration {
+    pub virtual_pool: Pubkey,
+    pub swap_target: SwapTarget,
+    // what the migration deposited, as found in the pool-creation CPI
+    pub reserves: Option<PoolReserves>,
+    pub trade_fee: Option<TradeFee>,
+}
+
+impl DbcMigration {
+    pub fn from_instruction(instruction: DbcInstruction) -> Self {
+        match instruction {
+            DbcInstruction::MigrateMeteoraDamm { accounts } => DbcMigration {
+                virtual_pool: accounts.virtual_pool,
+                swap_target: SwapTarget::DynamicAmm(SwapData::new(
+                    accounts.pool,
+                    accounts.token_a_mint,
+                    accounts.token_b_mint,
+                    accounts.a_vault,
+                    accounts.b_vault,
+                    accounts.a_token_vault,
+                    accounts.b_token_vault,
+                    accounts.a_vault_lp_mint,
+                    accounts.b_vault_lp_mint,
+                    accounts.a_vault_lp,
+                    accounts.b_vault_lp,
+                    accounts.vault_program,
+                    accounts.protocol_token_a_fee,
+                    accounts.protocol_token_b_fee,
+                )),
+                reserves: None,
+                trade_fee: None,
+            },
+            // DAMM v2 pools created by a migration are always base / quote
+            DbcInstruction::MigrationDammV2 { accounts } => DbcMigration {
+                virtual_pool: accounts.virtual_pool,
+                swap_target: SwapTarget::DammV2(DammV2SwapData {
+                    pool: accounts.pool,
+                    token_a_mint: accounts.base_mint,
+                    token_b_mint: accounts.quote_mint,
+                    token_a_vault: accounts.token_a_vault,
+                    token_b_vault: accounts.token_b_vault,
+                    token_a_program: accounts.token_base_program,
+                    token_b_program: accounts.token_quote_program,
+                }),
+                reserves: None,
+                trade_fee: None,
+            },
+        }
+    }
+
+    // Fills in the reserves from the pool-creation instruction the migration invoked.
+    // Returns false when `instruction` isn't the creation of this migration's pool.
+    pub fn apply_creation(&mut self, instruction: &Instruction) -> bool {
+        let pool = self.swap_target.pool();
+        // another program's instruction can carry the same discriminator and accounts
+        let program_id = match &self.swap_target {
+            SwapTarget::DynamicAmm(_) => METEORA_PROGRAM_ID,
+            SwapTarget::DammV2(_) => DAMM_V2_PROGRAM_ID,
+            SwapTarget::Dlmm(_) => return false,
+        };
+        if instruction.program_id != program_id {
+            return false;
+        }
+
+        let (reserves, trade_fee) = match &self.swap_target {
+            SwapTarget::DynamicAmm(_) => match MeteoraInstruction::decode(instruction) {
+                Ok(MeteoraInstruction::InitializePermissionlessConstantProductPoolWithConfig2 {
+                    accounts,
+                    args,
+                }) if accounts.pool == pool => (
+                    PoolReserves::ConstantProduct {
+                        amount_a: args.tokenAAmount,
+                        amount_b: args.tokenBAmount,
+                    },
+                    None,
+                ),
+                Ok(MeteoraInstruction::InitializePermissionlessConstantProductPoolWithConfig {
+                    accounts,
+                    args,
+                }) if accounts.pool == pool => (
+                    PoolReserves::ConstantProduct {
+                        amount_a: args.token_a_amount,
+                        amount_b: args.token_b_amount,
+                    },
+                    None,
+                ),
+                _ => return false,
+            },
+            SwapTarget::DammV2(_) => match DammV2Instruction::decode(instruction) {
+                Ok(DammV2Instruction::InitializePoolWithDynamicConfig { accounts, args })
+                    if accounts.pool == pool =>
+                {
+                    (
+                        PoolReserves::Concentrated {
+                            liquidity: args.liquidity,
+                            sqrt_price: args.sqrt_price,
+                        },
+                        Some(args.pool_fees.trade_fee()),
+                    )
+                }
+                Ok(DammV2Instruction::InitializePool { accounts, args }) if accounts.pool == pool => (
+                    PoolReserves::Concentrated {
+                        liquidity: args.liquidity,
+                        sqrt_price: args.sqrt_price,
+                    },
+                    None,
+                ),
+                _ => return false,
+            },
+            SwapTarget::Dlmm(_) => return false,
+        };
+
+        self.reserves = Some(reserves);
+        self.trade_fee = trade_fee;
+        true
+    }
+}
@@ -0,0 +1,550 @@
+use crate::activation::{ActivationInfo, ActivationType};
+use crate::core::decode::{args, instruction_accounts, split_discriminator, DecodeError};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+pub const DLMM_PROGRAM_ID: Pubkey = pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo");
+
+pub const INITIALIZE_LB_PAIR_DISC: [u8; 8] = [45, 154, 237, 210, 221, 15, 166, 92];
+pub const INITIALIZE_PERMISSION_LB_PAIR_DISC: [u8; 8] = [108, 102, 213, 85, 251, 3, 53, 21];
+pub const INITIALIZE_CUSTOMIZABLE_PERMISSIONLESS_LB_PAIR_DISC: [u8; 8] =
+    [46, 39, 41, 135, 111, 183, 200, 64];
+pub const INITIALIZE_BIN_ARRAY_DISC: [u8; 8] = [35, 86, 19, 185, 78, 212, 75, 211];
+pub const ADD_LIQUIDITY_DISC: [u8; 8] = [181, 157, 89, 67, 143, 182, 52, 72];
+pub const ADD_LIQUIDITY_BY_WEIGHT_DISC: [u8; 8] = [28, 140, 238, 99, 231, 162, 21, 149];
+pub const ADD_LIQUIDITY_BY_STRATEGY_DISC: [u8; 8] = [7, 3, 150, 127, 148, 40, 61, 200];
+pub const ADD_LIQUIDITY_ONE_SIDE_DISC: [u8; 8] = [94, 155, 103, 151, 70, 95, 220, 165];
+pub const ADD_LIQUIDITY_BY_STRATEGY_ONE_SIDE_DISC: [u8; 8] = [41, 5, 238, 175, 100, 225, 6, 205];
+pub const ADD_LIQUIDITY2_DISC: [u8; 8] = [228, 162, 78, 28, 70, 219, 116, 115];
+pub const ADD_LIQUIDITY_BY_STRATEGY2_DISC: [u8; 8] = [3, 221, 149, 218, 111, 141, 118, 213];
+pub const SWAP_DISC: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+
+pub const MAX_BIN_PER_ARRAY: i32 = 70;
+// bin array indexes outside of this range are tracked by the bitmap extension account
+pub const BIN_ARRAY_BITMAP_SIZE: i64 = 512;
+// active bin array plus the next ones in the swap direction
+pub const SWAP_BIN_ARRAYS: i64 = 3;
+// `LbPair` layout: discriminator, StaticParameters (32), VariableParameters (32),
+// bump_seed, bin_step_seed, pair_type, then active_id and bin_step
+const LB_PAIR_ACTIVE_ID_OFFSET: usize = 76;
+const LB_PAIR_BIN_STEP_OFFSET: usize = 80;
+// a pair that got no liquidity within this long is forgotten
+const PENDING_PAIR_TTL: Duration = Duration::from_secs(600);
+
+instruction_accounts!(InitializeLbPairAccounts {
+    lb_pair, bin_array_bitmap_extension, token_mint_x, token_mint_y, reserve_x, reserve_y,
+    oracle, preset_parameter, funder, token_program, system_program, rent, event_authority,
+    program,
+});
+
+instruction_accounts!(InitializePermissionLbPairAccounts {
+    base, lb_pair, bin_array_bitmap_extension, token_mint_x, token_mint_y, reserve_x, reserve_y,
+    oracle, admin, token_program, system_program, rent, event_authority, program,
+});
+
+instruction_accounts!(InitializeCustomizablePermissionlessLbPairAccounts {
+    lb_pair, bin_array_bitmap_extension, token_mint_x, token_mint_y, reserve_x, reserve_y,
+    oracle, user_token_x, funder, token_program, system_program, user_token_y, event_authority,
+    program,
+});
+
+instruction_accounts!(InitializeBinArrayAccounts {
+    lb_pair, bin_array, funder, system_program,
+});
+
+// leading accounts of every add_liquidity variant, the bin arrays follow at different places
+instruction_accounts!(AddLiquidityAccounts { position, lb_pair });
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct InitializeLbPairArgs {
+    pub active_id: i32,
+    pub bin_step: u16,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct InitPermissionPairIx {
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub base_factor: u16,
+    pub min_bin_id: i32,
+    pub max_bin_id: i32,
+    // 0 = slot, 1 = timestamp
+    pub activation_type: u8,
+}
+
+// leading fields of `CustomizableParams`, the trailing flags and padding are not needed
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct CustomizableParams {
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub base_factor: u16,
+    // 0 = slot, 1 = timestamp
+    pub activation_type: u8,
+    pub has_alpha_vault: bool,
+    pub activation_point: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub enum DlmmInstruction {
+    InitializeLbPair {
+        accounts: InitializeLbPairAccounts,
+        args: InitializeLbPairArgs,
+    },
+    InitializePermissionLbPair {
+        accounts: InitializePermissionLbPairAccounts,
+        args: InitPermissionPairIx,
+    },
+    InitializeCustomizablePermissionlessLbPair {
+        accounts: InitializeCustomizablePermissionlessLbPairAccounts,
+        args: CustomizableParams,
+    },
+    InitializeBinArray {
+        accounts: InitializeBinArrayAccounts,
+    },
+    AddLiquidity {
+        name: &'static str,
+        accounts: AddLiquidityAccounts,
+        // every account of the instruction, the bin arrays it fills are among them
+        touched: Vec<Pubkey>,
+    },
+}
+
+impl DlmmInstruction {
+    // Decodes the DLMM pair-creation instructions and what follows them until the first
+    // liquidity, everything else is reported as unknown.
+    pub fn decode(instruction: &Instruction) -> Result<Self, DecodeError> {
+        let accounts = instruction.accounts.as_slice();
+        let (discriminator, data) = split_discriminator(&instruction.data)?;
+
+        let decoded = match discriminator {
+            INITIALIZE_LB_PAIR_DISC => {
+                let name = "initialize_lb_pair";
+                DlmmInstruction::InitializeLbPair {
+                    accounts: InitializeLbPairAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            INITIALIZE_PERMISSION_LB_PAIR_DISC => {
+                let name = "initialize_permission_lb_pair";
+                DlmmInstruction::InitializePermissionLbPair {
+                    accounts: InitializePermissionLbPairAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            INITIALIZE_CUSTOMIZABLE_PERMISSIONLESS_LB_PAIR_DISC => {
+                let name = "initialize_customizable_permissionless_lb_pair";
+                DlmmInstruction::InitializeCustomizablePermissionlessLbPair {
+                    accounts: InitializeCustomizablePermissionlessLbPairAccounts::from_accounts(
+                        name, accounts,
+                    )?,
+                    args: args(name, data)?,
+                }
+            }
+            INITIALIZE_BIN_ARRAY_DISC => DlmmInstruction::InitializeBinArray {
+                accounts: InitializeBinArrayAccounts::from_accounts(
+                    "initialize_bin_array",
+                    accounts,
+                )?,
+            },
+            discriminator => {
+                let name = add_liquidity_name(discriminator)
+                    .ok_or(DecodeError::UnknownDiscriminator(discriminator))?;
+                DlmmInstruction::AddLiquidity {
+                    name,
+                    accounts: AddLiquidityAccounts::from_accounts(name, accounts)?,
+                    touched: accounts.iter().map(|account| account.pubkey).collect(),
+                }
+            }
+        };
+
+        Ok(decoded)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DlmmInstruction::InitializeLbPair { .. } => "initialize_lb_pair",
+            DlmmInstruction::InitializePermissionLbPair { .. } => "initialize_permission_lb_pair",
+            DlmmInstruction::InitializeCustomizablePermissionlessLbPair { .. } => {
+                "initialize_customizable_permissionless_lb_pair"
+            }
+            DlmmInstruction::InitializeBinArray { .. } => "initialize_bin_array",
+            DlmmInstruction::AddLiquidity { name, .. } => *name,
+        }
+    }
+}
+
+fn add_liquidity_name(discriminator: [u8; 8]) -> Option<&'static str> {
+    let name = match discriminator {
+        ADD_LIQUIDITY_DISC => "add_liquidity",
+        ADD_LIQUIDITY_BY_WEIGHT_DISC => "add_liquidity_by_weight",
+        ADD_LIQUIDITY_BY_STRATEGY_DISC => "add_liquidity_by_strategy",
+        ADD_LIQUIDITY_ONE_SIDE_DISC => "add_liquidity_one_side",
+        ADD_LIQUIDITY_BY_STRATEGY_ONE_SIDE_DISC => "add_liquidity_by_strategy_one_side",
+        ADD_LIQUIDITY2_DISC => "add_liquidity2",
+        ADD_LIQUIDITY_BY_STRATEGY2_DISC => "add_liquidity_by_strategy2",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[derive(Debug, Clone)]
+pub struct DlmmSwapData {
+    pub lb_pair: Pubkey,
+    pub token_x_mint: Pubkey,
+    pub token_y_mint: Pubkey,
+    pub reserve_x: Pubkey,
+    pub reserve_y: Pubkey,
+    pub oracle: Pubkey,
+    pub bin_array_bitmap_extension: Option<Pubkey>,
+    pub token_x_program: Pubkey,
+    pub token_y_program: Pubkey,
+    // bin arrays the swap may cross, passed as remaining accounts
+    pub bin_arrays: Vec<Pubkey>,
+}
+
+impl DlmmSwapData {
+    pub fn swap_instruction(
+        &self,
+        user: Pubkey,
+        user_token_in: Pubkey,
+        user_token_out: Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Instruction {
+        let mut data = Vec::with_capacity(24);
+        data.extend_from_slice(&SWAP_DISC);
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+        // anchor encodes a missing optional account as the program id
+        let bitmap_extension = self.bin_array_bitmap_extension.unwrap_or(DLMM_PROGRAM_ID);
+
+        let mut accounts = vec![
+            AccountMeta::new(self.lb_pair, false),
+            AccountMeta::new_readonly(bitmap_extension, false),
+            AccountMeta::new(self.reserve_x, false),
+            AccountMeta::new(self.reserve_y, false),
+            AccountMeta::new(user_token_in, false),
+            AccountMeta::new(user_token_out, false),
+            AccountMeta::new_readonly(self.token_x_mint, false),
+            AccountMeta::new_readonly(self.token_y_mint, false),
+            AccountMeta::new(self.oracle, false),
+            AccountMeta::new_readonly(DLMM_PROGRAM_ID, false), // host_fee_in (none)
+            AccountMeta::new_readonly(user, true),
+            AccountMeta::new_readonly(self.token_x_program, false),
+            AccountMeta::new_readonly(self.token_y_program, false),
+            AccountMeta::new_readonly(event_authority(), false),
+            AccountMeta::new_readonly(DLMM_PROGRAM_ID, false),
+        ];
+        accounts.extend(
+            self.bin_arrays
+                .iter()
+                .map(|bin_array| AccountMeta::new(*bin_array, false)),
+        );
+
+        Instruction {
+            program_id: DLMM_PROGRAM_ID,
+            accounts,
+            data,
+        }
+    }
+
+    // The bin arrays captured at creation only fit the first buy; later swaps derive
+    // them again from the pair's current active bin and their own direction.
+    pub fn for_swap(&self, active_id: i32, swap_for_y: bool) -> Self {
+        let active_index = bin_array_index(active_id);
+        let bin_array_bitmap_extension = if (-BIN_ARRAY_BITMAP_SIZE..BIN_ARRAY_BITMAP_SIZE)
+            .contains(&active_index)
+        {
+            None
+        } else {
+            Some(bin_array_bitmap_extension(&self.lb_pair))
+        };
+
+        DlmmSwapData {
+            bin_array_bitmap_extension,
+            bin_arrays: bin_arrays_for_swap(&self.lb_pair, active_id, swap_for_y),
+            ..self.clone()
+        }
+    }
+}
+
+// The part of an `LbPair` account that prices the pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LbPairState {
+    pub active_id: i32,
+    pub bin_step: u16,
+}
+
+impl LbPairState {
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let active_id = data.get(LB_PAIR_ACTIVE_ID_OFFSET..LB_PAIR_ACTIVE_ID_OFFSET + 4)?;
+        let bin_step = data.get(LB_PAIR_BIN_STEP_OFFSET..LB_PAIR_BIN_STEP_OFFSET + 2)?;
+        Some(LbPairState {
+            active_id: i32::from_le_bytes(active_id.try_into().ok()?),
+            bin_step: u16::from_le_bytes(bin_step.try_into().ok()?),
+        })
+    }
+
+    // price of one raw unit of X in raw units of Y at the active bin
+    pub fn price(&self) -> f64 {
+        (1.0 + self.bin_step as f64 / 10_000.0).powi(self.active_id)
+    }
+}
+
+// accounts every pair-creation variant has, under slightly different names
+struct PairAccounts {
+    lb_pair: Pubkey,
+    token_x_mint: Pubkey,
+    token_y_mint: Pubkey,
+    reserve_x: Pubkey,
+    reserve_y: Pubkey,
+    oracle: Pubkey,
+    token_program: Pubkey,
+    creator: Pubkey,
+}
+
+// A new DLMM pair seen in the stream.
+#[derive(Debug, Clone)]
+pub struct DlmmPairCreation {
+    pub swap_data: DlmmSwapData,
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub activation_point: Option<u64>,
+    pub activation_type: Option<ActivationType>,
+    pub creator: Pubkey,
+}
+
+impl DlmmPairCreation {
+    pub fn activation(&self) -> ActivationInfo {
+        ActivationInfo {
+            point: self.activation_point,
+            activation_type: self.activation_type,
+            config: None,
+        }
+    }
+
+    // `quote_mint` is the side we pay with (WSOL); the bin arrays are derived for buying the
+    // other side.
+    pub fn from_instruction(instruction: DlmmInstruction, quote_mint: &Pubkey) -> Option<Self> {
+        let (pair, active_id, bin_step, activation_point, activation_type) = match instruction {
+            DlmmInstruction::InitializeLbPair { accounts, args } => (
+                PairAccounts {
+                    lb_pair: accounts.lb_pair,
+                    token_x_mint: accounts.token_mint_x,
+                    token_y_mint: accounts.token_mint_y,
+                    reserve_x: accounts.reserve_x,
+                    reserve_y: accounts.reserve_y,
+                    oracle: accounts.oracle,
+                    token_program: accounts.token_program,
+                    creator: accounts.funder,
+                },
+                args.active_id,
+                args.bin_step,
+                None,
+                None,
+            ),
+            DlmmInstruction::InitializePermissionLbPair { accounts, args } => (
+                PairAccounts {
+                    lb_pair: accounts.lb_pair,
+                    token_x_mint: accounts.token_mint_x,
+                    token_y_mint: accounts.token_mint_y,
+                    reserve_x: accounts.reserve_x,
+                    reserve_y: accounts.reserve_y,
+                    oracle: accounts.oracle,
+                    token_program: accounts.token_program,
+                    creator: accounts.admin,
+                },
+                args.active_id,
+                args.bin_step,
+                None,
+                ActivationType::from_u8(args.activation_type),
+            ),
+            DlmmInstruction::InitializeCustomizablePermissionlessLbPair { accounts, args } => (
+                PairAccounts {
+                    lb_pair: accounts.lb_pair,
+                    token_x_mint: accounts.token_mint_x,
+                    token_y_mint: accounts.token_mint_y,
+                    reserve_x: accounts.reserve_x,
+                    reserve_y: accounts.reserve_y,
+                    oracle: accounts.oracle,
+                    token_program: accounts.token_program,
+                    creator: accounts.funder,
+                },
+                args.active_id,
+                args.bin_step,
+                args.activation_point,
+                ActivationType::from_u8(args.activation_type),
+            ),
+            DlmmInstruction::InitializeBinArray { .. } | DlmmInstruction::AddLiquidity { .. } => {
+                return None
+            }
+        };
+        let PairAccounts {
+            lb_pair,
+            token_x_mint,
+            token_y_mint,
+            reserve_x,
+            reserve_y,
+            oracle,
+            token_program,
+            creator,
+        } = pair;
+
+        if token_x_mint != *quote_mint && token_y_mint != *quote_mint {
+            return None;
+        }
+
+        let swap_data = DlmmSwapData {
+            lb_pair,
+            token_x_mint,
+            token_y_mint,
+            reserve_x,
+            reserve_y,
+            oracle,
+            bin_array_bitmap_extension: None,
+            // the pair-creation variants decoded here take a single token program
+            token_x_program: token_program,
+            token_y_program: token_program,
+            bin_arrays: Vec::new(),
+        };
+
+        // paying with X pushes the active bin down, paying with Y pushes it up
+        let swap_for_y = token_x_mint == *quote_mint;
+
+        Some(DlmmPairCreation {
+            swap_data: swap_data.for_swap(active_id, swap_for_y),
+            active_id,
+            bin_step,
+            activation_point,
+            activation_type,
+            creator,
+        })
+    }
+}
+
+struct PendingPair {
+    creation: DlmmPairCreation,
+    // initialized so far, by the pair's own instructions
+    bin_arrays: HashSet<Pubkey>,
+    created_at: Instant,
+}
+
+// A DLMM pair is created empty, its bin arrays and liquidity come with later instructions,
+// often in the same transaction. The pair waits here until its first liquidity add, the buy
+// then only names bin arrays that exist: a missing one makes the swap fail.
+#[derive(Default)]
+pub struct PendingPairs {
+    pairs: HashMap<Pubkey, PendingPair>,
+}
+
+impl PendingPairs {
+    pub fn insert(&mut self, creation: DlmmPairCreation) {
+        self.pairs
+            .retain(|_, pair| pair.created_at.elapsed() < PENDING_PAIR_TTL);
+        self.pairs.insert(
+            creation.swap_data.lb_pair,
+            PendingPair {
+                creation,
+                bin_arrays: HashSet::new(),
+                created_at: Instant::now(),
+            },
+        );
+    }
+
+    // The creation of the pair `instruction` added the first liquidity to, ready to buy.
+    pub fn update(&mut self, instruction: &DlmmInstruction) -> Option<DlmmPairCreation> {
+        match instruction {
+            DlmmInstruction::InitializeBinArray { accounts } => {
+                if let Some(pair) = self.pairs.get_mut(&accounts.lb_pair) {
+                    pair.bin_arrays.insert(accounts.bin_array);
+                }
+                None
+            }
+            DlmmInstruction::AddLiquidity {
+                accounts, touched, ..
+            } => {
+                let mut pair = self.pairs.remove(&accounts.lb_pair)?;
+                pair.bin_arrays.extend(touched);
+                let bin_arrays: Vec<Pubkey> = pair
+                    .creation
+                    .swap_data
+                    .bin_arrays
+                    .iter()
+                    .filter(|bin_array| pair.bin_arrays.contains(*bin_array))
+                    .cloned()
+                    .collect();
+                if bin_arrays.is_empty() {
+                    // liquidity behind the active bin only, nothing to buy yet
+                    self.pairs.insert(accounts.lb_pair, pair);
+                    return None;
+                }
+                pair.creation.swap_data.bin_arrays = bin_arrays;
+                Some(pair.creation)
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn bin_array_index(bin_id: i32) -> i64 {
+    bin_id.div_euclid(MAX_BIN_PER_ARRAY) as i64
+}
+
+pub fn bin_array(lb_pair: &Pubkey, index: i64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"bin_array", lb_pair.as_ref(), &index.to_le_bytes()],
+        &DLMM_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn bin_arrays_for_swap(lb_pair: &Pubkey, active_id: i32, swap_for_y: bool) -> Vec<Pubkey> {
+    let active_index = bin_array_index(active_id);
+    (0..SWAP_BIN_ARRAYS)
+        .map(|offset| {
+            if swap_for_y {
+                active_index - offset
+            } else {
+                active_index + offset
+            }
+        })
+        .map(|index| bin_array(lb_pair, index))
+        .collect()
+}
+
+// Bin arrays are only created once liquidity is added, passing one that doesn't
+// exist yet makes the swap fail. Keeps the derived order, drops missing arrays.
+pub async fn existing_bin_arrays(rpc_client: &RpcClient, bin_arrays: &[Pubkey]) -> Vec<Pubkey> {
+    match rpc_client.get_multiple_accounts(bin_arrays).await {
+        Ok(accounts) => bin_arrays
+            .iter()
+            .zip(accounts)
+            .filter(|(_, account)| {
+                account
+                    .as_ref()
+                    .map_or(false, |account| account.owner == DLMM_PROGRAM_ID)
+            })
+            .map(|(bin_array, _)| *bin_array)
+            .collect(),
+        Err(err) => {
+            // can't tell, better try with the active array than not at all
+            warn!("cannot fetch dlmm bin arrays: {:?}", err);
+            bin_arrays.iter().take(1).cloned().collect()
+        }
+    }
+}
+
+pub fn bin_array_bitmap_extension(lb_pair: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"bitmap", lb_pair.as_ref()], &DLMM_PROGRAM_ID).0
+}
+
+pub fn event_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority"], &DLMM_PROGRAM_ID).0
+}
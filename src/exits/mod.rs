@@ -0,0 +1,373 @@
+use crate::bench::Bench;
+use crate::config::{ExitsConfig, PingThingsArgs};
+use crate::dlmm::existing_bin_arrays;
+use crate::meteora::{SwapOrder, SwapSide, SwapTarget};
+use crate::positions::{PositionManager, PositionStatus};
+use crate::pricing::{fetch_tracker, PoolSnapshots, PriceSnapshot};
+use crate::quote::{apply_slippage, quote, TradeFee};
+use crate::WSOL_ACCOUNT_ID;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitReason {
+    // price / entry
+    TakeProfit(f64),
+    // percent below entry
+    StopLoss(f64),
+    // percent below the peak
+    TrailingStop(f64),
+    MaxHold(Duration),
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitReason::TakeProfit(multiple) => write!(f, "take profit at {:.2}x", multiple),
+            ExitReason::StopLoss(drop) => write!(f, "stop loss, {:.1}% below entry", drop),
+            ExitReason::TrailingStop(drop) => write!(f, "trailing stop, {:.1}% below peak", drop),
+            ExitReason::MaxHold(held) => write!(f, "held for {:?}", held),
+        }
+    }
+}
+
+// First rule that fires for a position, the price rules need an entry price and a current one.
+pub fn exit_reason(
+    config: &ExitsConfig,
+    entry_price: Option<f64>,
+    peak_price: f64,
+    price: Option<f64>,
+    held: Duration,
+) -> Option<ExitReason> {
+    if let (Some(entry_price), Some(price)) = (entry_price, price) {
+        let multiple = price / entry_price;
+        if let Some(take_profit_multiple) = config.take_profit_multiple {
+            if multiple >= take_profit_multiple {
+                return Some(ExitReason::TakeProfit(multiple));
+            }
+        }
+
+        let drop_from_entry = (1.0 - multiple) * 100.0;
+        if let Some(stop_loss_pct) = config.stop_loss_pct {
+            if drop_from_entry >= stop_loss_pct {
+                return Some(ExitReason::StopLoss(drop_from_entry));
+            }
+        }
+
+        let drop_from_peak = (1.0 - price / peak_price) * 100.0;
+        if let Some(trailing_stop_pct) = config.trailing_stop_pct {
+            if drop_from_peak >= trailing_stop_pct {
+                return Some(ExitReason::TrailingStop(drop_from_peak));
+            }
+        }
+    }
+
+    match config.max_hold_secs {
+        Some(max_hold_secs) if held >= Duration::from_secs(max_hold_secs) => {
+            Some(ExitReason::MaxHold(held))
+        }
+        _ => None,
+    }
+}
+
+struct TrackedPosition {
+    target: SwapTarget,
+    // the buy's executed price, or the first price seen for positions loaded at startup
+    entry_price: Option<f64>,
+    // false for positions bought before a restart, their fill is unknown
+    from_fill: bool,
+    peak_price: f64,
+    // when the position was first seen open, max hold counts from here
+    entered_at: Option<Instant>,
+}
+
+// Follows the price of open positions and sells back to WSOL once an exit rule fires.
+pub struct ExitMonitor {
+    config: ExitsConfig,
+    // sells are quoted with it when the pool's own fee isn't known
+    default_trade_fee: TradeFee,
+    owner: Pubkey,
+    bench: Bench,
+    rpc_client: Arc<RpcClient>,
+    positions: Arc<Mutex<PositionManager>>,
+    snapshots: PoolSnapshots,
+    tracked: HashMap<Pubkey, TrackedPosition>,
+}
+
+impl ExitMonitor {
+    pub fn new(
+        config: &PingThingsArgs,
+        bench: Bench,
+        rpc_client: Arc<RpcClient>,
+        positions: Arc<Mutex<PositionManager>>,
+        snapshots: PoolSnapshots,
+    ) -> Self {
+        let owner = Keypair::from_base58_string(config.private_key.as_str()).pubkey();
+        ExitMonitor {
+            config: config.exits.clone(),
+            default_trade_fee: TradeFee::from_bps(config.quote.default_trade_fee_bps),
+            owner,
+            bench,
+            rpc_client,
+            positions,
+            snapshots,
+            tracked: HashMap::new(),
+        }
+    }
+
+    pub fn check_interval(&self) -> Duration {
+        Duration::from_millis(self.config.check_interval_ms.max(1))
+    }
+
+    pub fn track(&mut self, target: &SwapTarget) {
+        self.insert(target, true);
+    }
+
+    // A position bought before a restart, its entry price is the first price seen.
+    pub fn track_existing(&mut self, target: &SwapTarget) {
+        self.insert(target, false);
+    }
+
+    fn insert(&mut self, target: &SwapTarget, from_fill: bool) {
+        if !self.config.enabled {
+            return;
+        }
+
+        self.tracked.insert(
+            target.pool(),
+            TrackedPosition {
+                target: target.clone(),
+                entry_price: None,
+                from_fill,
+                peak_price: 0.0,
+                entered_at: None,
+            },
+        );
+        info!("watching {} for exits", target.pool());
+    }
+
+    // Catches the rules that don't need a price update, i.e. max hold.
+    pub fn check_all(&mut self) {
+        for pool in self.tracked.keys().cloned().collect::<Vec<_>>() {
+            self.evaluate(&pool);
+        }
+    }
+
+    // Re-checks the rules of `pool`'s position against its latest snapshot.
+    pub fn evaluate(&mut self, pool: &Pubkey) {
+        let position = self
+            .positions
+            .lock()
+            .unwrap()
+            .get(pool)
+            .map(|position| (position.status, position.entry_price));
+        let fill_price = match position {
+            Some((PositionStatus::Open, fill_price)) => fill_price,
+            // buy still scheduled or sell in flight
+            Some((PositionStatus::Scheduled, _)) | Some((PositionStatus::Closing, _)) => return,
+            Some((PositionStatus::Closed, _)) | None => {
+                self.tracked.remove(pool);
+                return;
+            }
+        };
+
+        let Some(tracked) = self.tracked.get_mut(pool) else {
+            return;
+        };
+        let entered_at = *tracked.entered_at.get_or_insert_with(Instant::now);
+        let snapshot = self.snapshots.read().unwrap().get(pool).copied();
+
+        if tracked.from_fill {
+            tracked.entry_price = fill_price;
+        }
+        if let Some(snapshot) = &snapshot {
+            if tracked.entry_price.is_none() && !tracked.from_fill {
+                info!("entry price of {}: {:e} lamports per token unit", pool, snapshot.price);
+                tracked.entry_price = Some(snapshot.price);
+            }
+            tracked.peak_price = tracked.peak_price.max(snapshot.price);
+        }
+
+        let Some(reason) = exit_reason(
+            &self.config,
+            tracked.entry_price,
+            tracked.peak_price,
+            snapshot.map(|snapshot| snapshot.price),
+            entered_at.elapsed(),
+        ) else {
+            return;
+        };
+
+        info!("exiting position on {}: {}", pool, reason);
+        let target = tracked.target.clone();
+        self.positions
+            .lock()
+            .unwrap()
+            .set_status(pool, PositionStatus::Closing);
+        self.sell(target, snapshot);
+    }
+
+    fn sell(&self, target: SwapTarget, snapshot: Option<PriceSnapshot>) {
+        let bench = self.bench.clone();
+        let rpc_client = self.rpc_client.clone();
+        let positions = self.positions.clone();
+        let owner = self.owner;
+        let default_trade_fee = self.default_trade_fee;
+        let slippage_bps = self.config.sell_slippage_bps;
+
+        tokio::spawn(async move {
+            let pool = target.pool();
+            let sell_order = sell_order(
+                &rpc_client,
+                owner,
+                target,
+                snapshot,
+                default_trade_fee,
+                slippage_bps,
+            )
+            .await;
+
+            // a buy that failed closes its position once its landing times out, until then
+            // the tokens may still arrive and the next trigger tries again
+            let swap_order = match sell_order {
+                Ok(Some(swap_order)) => swap_order,
+                Ok(None) => {
+                    warn!("nothing to sell on {} yet", pool);
+                    reopen(&positions, &pool);
+                    return;
+                }
+                Err(err) => {
+                    error!("cannot build sell on {}: {:?}", pool, err);
+                    reopen(&positions, &pool);
+                    return;
+                }
+            };
+
+            let recent_blockhash = match rpc_client.get_latest_blockhash().await {
+                Ok(blockhash) => blockhash,
+                Err(err) => {
+                    error!("cannot fetch blockhash for sell on {}: {:?}", pool, err);
+                    reopen(&positions, &pool);
+                    return;
+                }
+            };
+
+            info!(
+                "selling {} on {} for at least {} lamports",
+                swap_order.amount_in, pool, swap_order.min_amount_out
+            );
+            let landed = bench
+                .send_swap_tx(recent_blockhash, swap_order, None)
+                .await
+                .await
+                .ok()
+                .flatten();
+            if landed.is_some() {
+                positions.lock().unwrap().close(&pool);
+            } else {
+                // the tokens are still there, the next trigger sells again
+                warn!("sell on {} did not land", pool);
+                reopen(&positions, &pool);
+            }
+        });
+    }
+}
+
+// Hands the position back to the exit rules. Only the buy's own landing closes a position
+// without a sell; this leaves one that follow_buy already closed alone.
+fn reopen(positions: &Mutex<PositionManager>, pool: &Pubkey) {
+    let mut positions = positions.lock().unwrap();
+    if positions.get(pool).map(|position| position.status) == Some(PositionStatus::Closing) {
+        positions.set_status(pool, PositionStatus::Open);
+    }
+}
+
+// Sells the whole token balance, None when the wallet holds none. Without a snapshot the
+// pool's reserves are fetched first; a sell is never sent without a bound on its output.
+async fn sell_order(
+    rpc_client: &RpcClient,
+    owner: Pubkey,
+    target: SwapTarget,
+    snapshot: Option<PriceSnapshot>,
+    default_trade_fee: TradeFee,
+    slippage_bps: u64,
+) -> anyhow::Result<Option<SwapOrder>> {
+    let amount_in = token_balance(rpc_client, owner, &target).await?;
+    if amount_in == 0 {
+        return Ok(None);
+    }
+
+    let snapshot = match snapshot {
+        Some(snapshot) => snapshot,
+        None => fetch_tracker(rpc_client, &target)
+            .await
+            .snapshot()
+            .ok_or_else(|| anyhow::anyhow!("no price to bound the sell with"))?,
+    };
+    let trade_fee = snapshot.trade_fee.unwrap_or(default_trade_fee);
+
+    let target = match (target, snapshot.active_id) {
+        // selling X moves the active bin down, selling Y moves it up
+        (SwapTarget::Dlmm(swap_data), Some(active_id)) => {
+            let swap_for_y = swap_data.token_x_mint != WSOL_ACCOUNT_ID;
+            let mut swap_data = swap_data.for_swap(active_id, swap_for_y);
+            swap_data.bin_arrays = existing_bin_arrays(rpc_client, &swap_data.bin_arrays).await;
+            SwapTarget::Dlmm(swap_data)
+        }
+        (target, _) => target,
+    };
+
+    let min_amount_out =
+        sell_min_amount_out(&target, &snapshot, amount_in, &trade_fee, slippage_bps);
+
+    Ok(Some(SwapOrder {
+        target,
+        side: SwapSide::Sell,
+        amount_in,
+        min_amount_out,
+    }))
+}
+
+// `owner`'s balance of the pool's token, as of the latest processed slot.
+pub async fn token_balance(
+    rpc_client: &RpcClient,
+    owner: Pubkey,
+    target: &SwapTarget,
+) -> anyhow::Result<u64> {
+    let mint = target.token_mint();
+    let token_account =
+        get_associated_token_address_with_program_id(&owner, &mint, &target.token_program(&mint));
+    let balance = rpc_client
+        .get_token_account_balance_with_commitment(&token_account, CommitmentConfig::processed())
+        .await?
+        .value;
+    Ok(balance.amount.parse()?)
+}
+
+// Quotes the sell on the observed reserves when the pool has them, otherwise takes the
+// spot price.
+fn sell_min_amount_out(
+    target: &SwapTarget,
+    snapshot: &PriceSnapshot,
+    amount_in: u64,
+    trade_fee: &TradeFee,
+    slippage_bps: u64,
+) -> u64 {
+    if let Some(reserves) = &snapshot.reserves {
+        let (mint_a, _) = target.mints();
+        let a_to_b = mint_a != WSOL_ACCOUNT_ID;
+        if let Some(quote) = quote(reserves, trade_fee, a_to_b, amount_in, None, slippage_bps) {
+            return quote.min_amount_out;
+        }
+    }
+
+    apply_slippage((amount_in as f64 * snapshot.price) as u64, slippage_bps)
+}
@@ -0,0 +1,154 @@
+use crate::config::FiltersConfig;
+use crate::meteora::PoolCandidate;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    UnknownLiquidity,
+    LiquidityTooLow { sol: f64, min: f64 },
+    LiquidityTooHigh { sol: f64, max: f64 },
+    MintNotAllowed(Pubkey),
+    MintDenied(Pubkey),
+    UnknownCreator,
+    CreatorNotAllowed(Pubkey),
+    CreatorDenied(Pubkey),
+    ConfigNotAllowed(Option<Pubkey>),
+    ActivationTooFar { slots: u64, max: u64 },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::UnknownLiquidity => write!(f, "initial SOL liquidity unknown"),
+            Rejection::LiquidityTooLow { sol, min } => {
+                write!(f, "initial liquidity {} SOL below minimum {} SOL", sol, min)
+            }
+            Rejection::LiquidityTooHigh { sol, max } => {
+                write!(f, "initial liquidity {} SOL above maximum {} SOL", sol, max)
+            }
+            Rejection::MintNotAllowed(mint) => write!(f, "mint {} not in allowlist", mint),
+            Rejection::MintDenied(mint) => write!(f, "mint {} is denylisted", mint),
+            Rejection::UnknownCreator => write!(f, "creator unknown"),
+            Rejection::CreatorNotAllowed(creator) => {
+                write!(f, "creator {} not in allowlist", creator)
+            }
+            Rejection::CreatorDenied(creator) => write!(f, "creator {} is denylisted", creator),
+            Rejection::ConfigNotAllowed(config) => {
+                write!(f, "pool config {:?} not in allowed configs", config)
+            }
+            Rejection::ActivationTooFar { slots, max } => {
+                write!(f, "activates in {} slots, more than {}", slots, max)
+            }
+        }
+    }
+}
+
+// Rules a pool has to pass before we buy it, built once from `filters` in config.yaml.
+pub struct PoolFilter {
+    min_sol_liquidity: Option<f64>,
+    max_sol_liquidity: Option<f64>,
+    require_known_liquidity: bool,
+    mint_allowlist: HashSet<Pubkey>,
+    mint_denylist: HashSet<Pubkey>,
+    creator_allowlist: HashSet<Pubkey>,
+    creator_denylist: HashSet<Pubkey>,
+    pool_configs: HashSet<Pubkey>,
+    max_activation_slots: Option<u64>,
+    slot_duration: Duration,
+}
+
+impl PoolFilter {
+    pub fn new(config: &FiltersConfig, slot_duration: Duration) -> Self {
+        PoolFilter {
+            min_sol_liquidity: config.min_sol_liquidity,
+            max_sol_liquidity: config.max_sol_liquidity,
+            require_known_liquidity: config.require_known_liquidity,
+            mint_allowlist: pubkeys(&config.mint_allowlist),
+            mint_denylist: pubkeys(&config.mint_denylist),
+            creator_allowlist: pubkeys(&config.creator_allowlist),
+            creator_denylist: pubkeys(&config.creator_denylist),
+            pool_configs: pubkeys(&config.pool_configs),
+            max_activation_slots: config.max_activation_slots,
+            slot_duration,
+        }
+    }
+
+    // `wait` is how long until the pool activates, None when it already is.
+    pub fn check(&self, candidate: &PoolCandidate, wait: Option<Duration>) -> Result<(), Rejection> {
+        self.check_liquidity(candidate)?;
+
+        let mint = candidate.target.token_mint();
+        if !self.mint_allowlist.is_empty() && !self.mint_allowlist.contains(&mint) {
+            return Err(Rejection::MintNotAllowed(mint));
+        }
+        if self.mint_denylist.contains(&mint) {
+            return Err(Rejection::MintDenied(mint));
+        }
+
+        match candidate.creator {
+            Some(creator) => {
+                if !self.creator_allowlist.is_empty() && !self.creator_allowlist.contains(&creator) {
+                    return Err(Rejection::CreatorNotAllowed(creator));
+                }
+                if self.creator_denylist.contains(&creator) {
+                    return Err(Rejection::CreatorDenied(creator));
+                }
+            }
+            None if !self.creator_allowlist.is_empty() => return Err(Rejection::UnknownCreator),
+            None => {}
+        }
+
+        if !self.pool_configs.is_empty()
+            && !candidate
+                .config
+                .map_or(false, |config| self.pool_configs.contains(&config))
+        {
+            return Err(Rejection::ConfigNotAllowed(candidate.config));
+        }
+
+        if let (Some(max), Some(wait)) = (self.max_activation_slots, wait) {
+            let slot_micros = self.slot_duration.as_micros().max(1);
+            let slots = (wait.as_micros() / slot_micros) as u64;
+            if slots > max {
+                return Err(Rejection::ActivationTooFar { slots, max });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_liquidity(&self, candidate: &PoolCandidate) -> Result<(), Rejection> {
+        let Some(lamports) = candidate.sol_amount() else {
+            return if self.require_known_liquidity {
+                Err(Rejection::UnknownLiquidity)
+            } else {
+                Ok(())
+            };
+        };
+
+        let sol = lamports as f64 / LAMPORTS_PER_SOL as f64;
+        if let Some(min) = self.min_sol_liquidity {
+            if sol < min {
+                return Err(Rejection::LiquidityTooLow { sol, min });
+            }
+        }
+        if let Some(max) = self.max_sol_liquidity {
+            if sol > max {
+                return Err(Rejection::LiquidityTooHigh { sol, max });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn pubkeys(addresses: &[String]) -> HashSet<Pubkey> {
+    addresses
+        .iter()
+        .map(|address| Pubkey::from_str(address).expect("invalid address in filters config"))
+        .collect()
+}
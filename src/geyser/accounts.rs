@@ -0,0 +1,164 @@
+use crate::config::GeyserReconnectConfig;
+use crate::geyser::{backoff_delay, connect, process_stream, GeyserResult, Handled};
+use futures::{stream, Stream};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::pin::pin;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::watch;
+use tracing::{error, info};
+use yellowstone_grpc_proto::geyser::{
+    subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest,
+    SubscribeRequestFilterAccounts, SubscribeUpdateAccount,
+};
+
+#[derive(Debug, Clone)]
+pub struct AccountUpdate {
+    // name of the endpoint that delivered the update
+    pub source: String,
+    pub account: Pubkey,
+    pub data: Vec<u8>,
+    pub slot: u64,
+}
+
+// Streams updates of a set of accounts that changes at runtime, e.g. the reserves of the
+// pools we hold positions in. Every change of the set replaces the subscription in place.
+pub struct GeyserAccountSubscriber {
+    pub name: String,
+    pub endpoint: String,
+    pub x_token: Option<String>,
+    pub commitment: Option<CommitmentLevel>,
+    pub accounts: watch::Receiver<Vec<Pubkey>>,
+    pub reconnect: GeyserReconnectConfig,
+}
+
+impl GeyserAccountSubscriber {
+    pub fn new(
+        name: String,
+        endpoint: String,
+        x_token: Option<String>,
+        commitment: Option<CommitmentLevel>,
+        accounts: watch::Receiver<Vec<Pubkey>>,
+        reconnect: GeyserReconnectConfig,
+    ) -> Self {
+        GeyserAccountSubscriber {
+            name,
+            endpoint,
+            x_token,
+            commitment,
+            accounts,
+            reconnect,
+        }
+    }
+
+    fn subscribe_request(&self, accounts: &[Pubkey]) -> SubscribeRequest {
+        let mut account_filters = HashMap::new();
+        // an account filter without accounts or owners matches every account on chain
+        if !accounts.is_empty() {
+            account_filters.insert(
+                "watched_accounts".to_string(),
+                SubscribeRequestFilterAccounts {
+                    account: accounts.iter().map(|account| account.to_string()).collect(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        SubscribeRequest {
+            accounts: account_filters,
+            commitment: self.commitment.map(|x| x as i32),
+            ..Default::default()
+        }
+    }
+
+    fn account_update(&self, account_update: SubscribeUpdateAccount) -> Option<AccountUpdate> {
+        let account_info = account_update.account?;
+        let account = Pubkey::try_from(account_info.pubkey.as_slice()).ok()?;
+        Some(AccountUpdate {
+            source: self.name.clone(),
+            account,
+            data: account_info.data,
+            slot: account_update.slot,
+        })
+    }
+
+    // A new request every time the watched set changes, ends once its sender is gone.
+    fn resubscriptions<'a>(
+        &'a self,
+        accounts: &'a mut watch::Receiver<Vec<Pubkey>>,
+    ) -> impl Stream<Item = SubscribeRequest> + 'a {
+        stream::unfold(accounts, move |accounts| async move {
+            if accounts.changed().await.is_err() {
+                info!("watched accounts dropped, stopping {}", self.name);
+                return None;
+            }
+            let request = self.subscribe_request(&accounts.borrow_and_update());
+            Some((request, accounts))
+        })
+    }
+
+    fn handle_update(
+        &self,
+        update: UpdateOneof,
+        sender: &UnboundedSender<AccountUpdate>,
+    ) -> Handled {
+        if let UpdateOneof::Account(account_update) = update {
+            if let Some(update) = self.account_update(account_update) {
+                if sender.send(update).is_err() {
+                    error!("account receiver dropped, stopping {}", self.name);
+                    return Handled::Stop;
+                }
+            }
+        }
+        // watched accounts can stay quiet for long, pings and pongs count as signs of life too
+        Handled::Received
+    }
+
+    pub async fn consume(&self, sender: UnboundedSender<AccountUpdate>) -> GeyserResult<()> {
+        let mut accounts = self.accounts.clone();
+        let mut failures: u32 = 0;
+
+        loop {
+            if sender.is_closed() || accounts.has_changed().is_err() {
+                info!("account updates no longer needed, stopping {}", self.name);
+                return Ok(());
+            }
+
+            let mut client = match connect(&self.endpoint, &self.x_token).await {
+                Ok(client) => Some(client),
+                Err(err) => {
+                    error!("Failed to connect to geyser {}: {:?}", self.endpoint, err);
+                    None
+                }
+            };
+
+            if let Some(client) = client.as_mut() {
+                // resubscribing always starts from the current set
+                let request = self.subscribe_request(&accounts.borrow_and_update());
+                match client.subscribe_with_request(Some(request)).await {
+                    Ok((mut subscribe_tx, mut stream)) => {
+                        info!("subscribed to geyser accounts on {}", self.endpoint);
+                        let received_any = process_stream(
+                            &self.endpoint,
+                            &self.reconnect,
+                            &mut subscribe_tx,
+                            &mut stream,
+                            pin!(self.resubscriptions(&mut accounts)),
+                            |update| self.handle_update(update, &sender),
+                        )
+                        .await;
+                        if received_any {
+                            failures = 0;
+                        }
+                    }
+                    Err(err) => error!("Failed to subscribe to accounts: {:?}", err),
+                }
+            }
+
+            failures += 1;
+            let delay = backoff_delay(&self.reconnect, failures);
+            info!("reconnecting to geyser accounts {} in {:?}", self.endpoint, delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
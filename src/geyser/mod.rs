@@ -1,66 +1,301 @@
 use async_trait::async_trait;
-use futures::{sink::SinkExt, StreamExt};
+use futures::{sink::SinkExt, Sink, Stream, StreamExt};
+use rand::Rng;
 use solana_sdk::{account::Account, pubkey::Pubkey, signature::Signature};
 use std::collections::HashSet;
+use solana_sdk::transaction::VersionedTransaction;
+use solana_transaction_status::TransactionStatusMeta;
 use std::convert::TryFrom;
 use std::time::Duration;
 use std::{collections::HashMap, sync::Arc};
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 use tokio::sync::{mpsc::UnboundedSender, RwLock};
+use tokio::time::Instant;
 use tokio_util::sync::CancellationToken;
 use yellowstone_grpc_client::GeyserGrpcClient;
+use yellowstone_grpc_proto::tonic::service::Interceptor;
 use yellowstone_grpc_proto::tonic::transport::ClientTlsConfig;
+use yellowstone_grpc_proto::tonic::Status;
 use yellowstone_grpc_proto::{
     convert_from::{create_tx_meta, create_tx_versioned},
     geyser::{
         subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest,
         SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions, SubscribeRequestPing,
+        SubscribeUpdate, SubscribeUpdateTransaction,
     },
 };
 
+pub mod accounts;
+pub mod multiplexer;
+pub mod websocket;
+
+#[derive(Debug, Clone)]
+pub struct TransactionUpdate {
+    // name of the endpoint that delivered the update
+    pub source: String,
+    pub signature: Signature,
+    pub transaction: VersionedTransaction,
+    pub meta: TransactionStatusMeta,
+    pub is_vote: bool,
+    pub slot: u64,
+}
+
 #[derive(Debug)]
 pub struct YellowstoneGrpcGeyserClient {
+    pub name: String,
     pub endpoint: String,
     pub x_token: Option<String>,
     pub commitment: Option<CommitmentLevel>,
     pub account_filters: HashMap<String, SubscribeRequestFilterAccounts>,
     pub transaction_filters: HashMap<String, SubscribeRequestFilterTransactions>,
     pub account_deletions_tracked: Arc<RwLock<HashSet<Pubkey>>>,
+    pub reconnect: GeyserReconnectConfig,
 }
 
 impl YellowstoneGrpcGeyserClient {
     pub fn new(
+        name: String,
         endpoint: String,
         x_token: Option<String>,
         commitment: Option<CommitmentLevel>,
         account_filters: HashMap<String, SubscribeRequestFilterAccounts>,
         transaction_filters: HashMap<String, SubscribeRequestFilterTransactions>,
         account_deletions_tracked: Arc<RwLock<HashSet<Pubkey>>>,
+        reconnect: GeyserReconnectConfig,
     ) -> Self {
         YellowstoneGrpcGeyserClient {
+            name,
             endpoint,
             x_token,
             commitment,
             account_filters,
             transaction_filters,
             account_deletions_tracked,
+            reconnect,
+        }
+    }
+
+    async fn connect(&self) -> GeyserResult<GeyserGrpcClient<impl Interceptor>> {
+        connect(&self.endpoint, &self.x_token).await
+    }
+
+    fn subscribe_request(&self) -> SubscribeRequest {
+        SubscribeRequest {
+            slots: HashMap::new(),
+            accounts: self.account_filters.clone(),
+            transactions: self.transaction_filters.clone(),
+            transactions_status: HashMap::new(),
+            entry: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            commitment: self.commitment.map(|x| x as i32),
+            accounts_data_slice: vec![],
+            ping: None,
+        }
+    }
+
+    fn transaction_update(
+        &self,
+        transaction_update: SubscribeUpdateTransaction,
+    ) -> Option<TransactionUpdate> {
+        let Some(transaction_info) = transaction_update.transaction else {
+            log::error!("No transaction info in `UpdateOneof::Transaction` at slot {}", transaction_update.slot);
+            return None;
+        };
+        let signature = Signature::try_from(transaction_info.signature).ok()?;
+        let yellowstone_transaction = transaction_info.transaction?;
+        let yellowstone_tx_meta = transaction_info.meta?;
+        let versioned_transaction = create_tx_versioned(yellowstone_transaction).ok()?;
+        let meta_original = match create_tx_meta(yellowstone_tx_meta) {
+            Ok(meta) => meta,
+            Err(err) => {
+                log::error!("Failed to create transaction meta: {:?}", err);
+                return None;
+            }
+        };
+
+        Some(TransactionUpdate {
+            source: self.name.clone(),
+            signature,
+            transaction: versioned_transaction,
+            meta: meta_original,
+            is_vote: transaction_info.is_vote,
+            slot: transaction_update.slot,
+        })
+    }
+
+    fn handle_update(
+        &self,
+        update: UpdateOneof,
+        sender: &UnboundedSender<TransactionUpdate>,
+    ) -> Handled {
+        match update {
+            UpdateOneof::Transaction(transaction_update) => {
+                if let Some(update) = self.transaction_update(transaction_update) {
+                    if sender.send(update).is_err() {
+                        error!("transaction receiver dropped, stopping {}", self.name);
+                        return Handled::Stop;
+                    }
+                }
+                Handled::Received
+            }
+            // a stalled stream can still answer pings, only real updates count
+            UpdateOneof::Ping(_) | UpdateOneof::Pong(_) => Handled::Ignored,
+            _ => Handled::Received,
+        }
+    }
+}
+
+pub async fn connect(
+    endpoint: &str,
+    x_token: &Option<String>,
+) -> GeyserResult<GeyserGrpcClient<impl Interceptor>> {
+    GeyserGrpcClient::build_from_shared(endpoint.to_string())
+        .map_err(|err| Error::Custom(err.to_string()))?
+        .x_token(x_token.clone())
+        .map_err(|err| Error::Custom(err.to_string()))?
+        .connect_timeout(Duration::from_secs(15))
+        .timeout(Duration::from_secs(15))
+        .tls_config(ClientTlsConfig::new().with_enabled_roots())
+        .map_err(|err| Error::Custom(err.to_string()))?
+        .connect()
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))
+}
+
+// What a subscription made of an update `process_stream` handed it.
+pub enum Handled {
+    // counts as a sign of life of the stream
+    Received,
+    Ignored,
+    // the updates are no longer needed
+    Stop,
+}
+
+// Drives a single subscription until it errors, closes or stalls, answering pings and sending
+// keepalives on the way. Every update goes to `handle`, every request from `requests` replaces
+// the subscription in place and the end of `requests` stops it.
+// Returns true if at least one update was received, so the caller can reset its backoff.
+pub async fn process_stream<S, T, R>(
+    endpoint: &str,
+    reconnect: &GeyserReconnectConfig,
+    subscribe_tx: &mut S,
+    stream: &mut T,
+    mut requests: R,
+    mut handle: impl FnMut(UpdateOneof) -> Handled,
+) -> bool
+where
+    S: Sink<SubscribeRequest> + Unpin,
+    S::Error: std::fmt::Debug,
+    T: Stream<Item = Result<SubscribeUpdate, Status>> + Unpin,
+    R: Stream<Item = SubscribeRequest> + Unpin,
+{
+    let stall_timeout = Duration::from_secs(reconnect.stall_timeout_secs);
+    let mut ping_interval =
+        tokio::time::interval(Duration::from_secs(reconnect.ping_interval_secs.max(1)));
+    let mut ping_id: i32 = 0;
+    let mut last_update = Instant::now();
+    let mut received_any = false;
+
+    loop {
+        tokio::select! {
+            message = stream.next() => {
+                let msg = match message {
+                    Some(Ok(msg)) => msg,
+                    Some(Err(error)) => {
+                        error!("Geyser stream error from {}: {error:?}", endpoint);
+                        return received_any;
+                    }
+                    None => {
+                        warn!("Geyser stream closed by {}", endpoint);
+                        return received_any;
+                    }
+                };
+                let Some(update) = msg.update_oneof else {
+                    continue;
+                };
+
+                match &update {
+                    // the server pings idle connections, answering keeps load balancers from
+                    // dropping us
+                    UpdateOneof::Ping(_) => {
+                        ping_id = ping_id.wrapping_add(1);
+                        if let Err(err) = subscribe_tx.send(ping_request(ping_id)).await {
+                            error!("Failed to answer geyser ping: {:?}", err);
+                            return received_any;
+                        }
+                    }
+                    UpdateOneof::Pong(pong) => debug!("geyser pong {}", pong.id),
+                    _ => {}
+                }
+
+                match handle(update) {
+                    Handled::Received => {
+                        last_update = Instant::now();
+                        received_any = true;
+                    }
+                    Handled::Ignored => {}
+                    Handled::Stop => return received_any,
+                }
+            }
+            request = requests.next() => {
+                let Some(request) = request else {
+                    return received_any;
+                };
+                if let Err(err) = subscribe_tx.send(request).await {
+                    error!("Failed to update geyser subscription: {:?}", err);
+                    return received_any;
+                }
+            }
+            _ = ping_interval.tick() => {
+                ping_id = ping_id.wrapping_add(1);
+                if let Err(err) = subscribe_tx.send(ping_request(ping_id)).await {
+                    error!("Failed to send geyser keepalive ping: {:?}", err);
+                    return received_any;
+                }
+            }
+            _ = tokio::time::sleep_until(last_update + stall_timeout) => {
+                warn!(
+                    "No geyser update from {} for {:?}, forcing reconnect",
+                    endpoint, stall_timeout
+                );
+                return received_any;
+            }
         }
     }
 }
 
+// exponential backoff with "equal jitter": half of the delay is fixed, the other half random
+pub fn backoff_delay(reconnect: &GeyserReconnectConfig, failures: u32) -> Duration {
+    let exp = reconnect
+        .initial_backoff_ms
+        .saturating_mul(1u64 << failures.saturating_sub(1).min(16));
+    let capped = exp.min(reconnect.max_backoff_ms).max(1);
+    let jitter = rand::thread_rng().gen_range(0..=capped / 2);
+    Duration::from_millis(capped / 2 + jitter)
+}
+
+pub fn ping_request(id: i32) -> SubscribeRequest {
+    SubscribeRequest {
+        ping: Some(SubscribeRequestPing { id }),
+        ..Default::default()
+    }
+}
+
 pub type GeyserResult<T> = Result<T, Error>;
 
+// Anything that can feed transactions into the multiplexer: geyser, websocket logs, ...
 #[async_trait]
-pub trait YellowstoneGrpcGeyser: Send + Sync {
+pub trait TransactionSource: Send + Sync {
+    fn name(&self) -> String;
     async fn consume(
         &self,
-        meteora_controller: MeteoraController
+        sender: UnboundedSender<TransactionUpdate>
     ) -> GeyserResult<()>;
 }
 
 use thiserror::Error;
-use crate::config::PingThingsArgs;
-use crate::meteora::MeteoraController;
+use crate::config::{GeyserReconnectConfig, PingThingsArgs};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -69,121 +304,80 @@ pub enum Error {
 }
 
 #[async_trait]
-impl YellowstoneGrpcGeyser for YellowstoneGrpcGeyserClient {
+impl TransactionSource for YellowstoneGrpcGeyserClient {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
     async fn consume(
         &self,
-        mut meteora_controller: MeteoraController
+        sender: UnboundedSender<TransactionUpdate>
     ) -> GeyserResult<()> {
-        // info!("INSIDE CONSUME");
-        let endpoint = self.endpoint.clone();
-        let x_token = self.x_token.clone();
-        let commitment = self.commitment;
-        let account_filters = self.account_filters.clone();
-        let transaction_filters = self.transaction_filters.clone();
-        let account_deletions_tracked = self.account_deletions_tracked.clone();
-
-        let mut geyser_client = GeyserGrpcClient::build_from_shared(endpoint)
-            .map_err(|err| Error::Custom(err.to_string()))?
-            .x_token(x_token)
-            .map_err(|err| Error::Custom(err.to_string()))?
-            .connect_timeout(Duration::from_secs(15))
-            .timeout(Duration::from_secs(15))
-            .tls_config(ClientTlsConfig::new().with_enabled_roots())
-            .map_err(|err| Error::Custom(err.to_string()))?
-            .connect()
-            .await
-            .map_err(|err| Error::Custom(err.to_string()))?;
-
-        let _ = tokio::spawn(async move {
-            let subscribe_request = SubscribeRequest {
-                slots: HashMap::new(),
-                accounts: account_filters,
-                transactions: transaction_filters,
-                transactions_status: HashMap::new(),
-                entry: HashMap::new(),
-                blocks: HashMap::new(),
-                blocks_meta: HashMap::new(),
-                commitment: commitment.map(|x| x as i32),
-                accounts_data_slice: vec![],
-                ping: None,
+        let mut geyser_client = None;
+        let mut failures: u32 = 0;
+
+        loop {
+            if sender.is_closed() {
+                info!("transaction receiver dropped, stopping geyser {}", self.name);
+                return Ok(());
+            }
+
+            if geyser_client.is_none() {
+                match self.connect().await {
+                    Ok(client) => {
+                        info!("connected to geyser {}", self.endpoint);
+                        geyser_client = Some(client);
+                    }
+                    Err(err) => {
+                        failures += 1;
+                        let delay = backoff_delay(&self.reconnect, failures);
+                        error!(
+                            "Failed to connect to geyser {} (attempt {}): {:?}, retrying in {:?}",
+                            self.endpoint, failures, err, delay
+                        );
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
+                }
+            }
+
+            let Some(client) = geyser_client.as_mut() else {
+                continue;
             };
 
-            loop {
-                tokio::select! {
-                    result = geyser_client.subscribe_with_request(Some(subscribe_request.clone())) => {
-                        match result {
-                            Ok((mut subscribe_tx, mut stream)) => {
-                                while let Some(message) = stream.next().await {
-                                    match message {
-                                        Ok(msg) => match msg.update_oneof {
-                                            Some(UpdateOneof::Transaction(transaction_update)) => {
-                                                let start_time = std::time::Instant::now();
-
-                                                if let Some(transaction_info) =
-                                                    transaction_update.transaction
-                                                {
-                                                    let Ok(signature) =
-                                                        Signature::try_from(transaction_info.signature)
-                                                    else {
-                                                        continue;
-                                                    };
-                                                    let Some(yellowstone_transaction) =
-                                                        transaction_info.transaction
-                                                    else {
-                                                        continue;
-                                                    };
-                                                    let Some(yellowstone_tx_meta) = transaction_info.meta
-                                                    else {
-                                                        continue;
-                                                    };
-                                                    let Ok(versioned_transaction) =
-                                                        create_tx_versioned(yellowstone_transaction)
-                                                    else {
-                                                        continue;
-                                                    };
-                                                    let meta_original = match create_tx_meta(
-                                                        yellowstone_tx_meta,
-                                                    ) {
-                                                        Ok(meta) => meta,
-                                                        Err(err) => {
-                                                            log::error!(
-                                                                "Failed to create transaction meta: {:?}",
-                                                                err
-                                                            );
-                                                            continue;
-                                                        }
-                                                    };
-                                                    // info!("signature {:?}", signature);
-                                                     let  _ = meteora_controller.transaction_handler(
-                                                        signature,
-                                                        versioned_transaction,
-                                                        meta_original,
-                                                        transaction_info.is_vote,
-                                                        transaction_update.slot
-                                                     ).await;
-                                                } else {
-                                                    log::error!("No transaction info in `UpdateOneof::Transaction` at slot {}", transaction_update.slot);
-                                                }
-                                            }
-
-                                            _ => {}
-                                        },
-                                        Err(error) => {
-                                            log::error!("Geyser stream error: {error:?}");
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                log::error!("Failed to subscribe: {:?}", e);
-                            }
-                        }
+            match client.subscribe_with_request(Some(self.subscribe_request())).await {
+                Ok((mut subscribe_tx, mut stream)) => {
+                    info!("subscribed to geyser {}", self.endpoint);
+                    let received_any = process_stream(
+                        &self.endpoint,
+                        &self.reconnect,
+                        &mut subscribe_tx,
+                        &mut stream,
+                        futures::stream::pending(),
+                        |update| self.handle_update(update, &sender),
+                    )
+                    .await;
+                    if received_any {
+                        failures = 0;
                     }
                 }
+                Err(e) => {
+                    log::error!("Failed to subscribe: {:?}", e);
+                }
+            }
+
+            failures += 1;
+            if failures >= self.reconnect.max_failures_before_rebuild {
+                warn!(
+                    "{} consecutive geyser failures, rebuilding client for {}",
+                    failures, self.endpoint
+                );
+                geyser_client = None;
             }
-        }).await;
 
-        Ok(())
+            let delay = backoff_delay(&self.reconnect, failures);
+            info!("reconnecting to geyser {} in {:?}", self.endpoint, delay);
+            tokio::time::sleep(delay).await;
+        }
     }
 }
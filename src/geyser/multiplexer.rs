@@ -0,0 +1,131 @@
+use crate::geyser::accounts::AccountUpdate;
+use crate::geyser::TransactionUpdate;
+use crate::meteora::MeteoraController;
+use solana_sdk::signature::Signature;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::info;
+
+// how many signatures we remember for deduplication; endpoints rarely lag more than a few seconds
+const DEDUP_CAPACITY: usize = 50_000;
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Default, Debug)]
+struct EndpointStats {
+    // number of signatures this endpoint delivered first
+    wins: u64,
+    // number of signatures this endpoint delivered after another endpoint
+    losses: u64,
+    // sum of how far behind the winner this endpoint was, in microseconds
+    lag_micros: u128,
+}
+
+// Merges transaction streams from several geyser endpoints and hands every
+// signature to the controller exactly once, from whichever endpoint was first.
+// Account updates of watched pools are passed through as they come.
+pub struct GeyserMultiplexer {
+    seen: HashMap<Signature, (String, Instant)>,
+    order: VecDeque<Signature>,
+    stats: HashMap<String, EndpointStats>,
+}
+
+impl GeyserMultiplexer {
+    pub fn new() -> Self {
+        GeyserMultiplexer {
+            seen: HashMap::with_capacity(DEDUP_CAPACITY),
+            order: VecDeque::with_capacity(DEDUP_CAPACITY),
+            stats: HashMap::new(),
+        }
+    }
+
+    pub async fn run(
+        mut self,
+        mut receiver: UnboundedReceiver<TransactionUpdate>,
+        mut account_receiver: UnboundedReceiver<AccountUpdate>,
+        mut meteora_controller: MeteoraController,
+    ) {
+        let mut report_interval = tokio::time::interval(REPORT_INTERVAL);
+        report_interval.tick().await;
+        let mut exit_interval = tokio::time::interval(meteora_controller.exit_check_interval());
+
+        loop {
+            tokio::select! {
+                update = receiver.recv() => {
+                    let Some(update) = update else {
+                        info!("all geyser endpoints stopped");
+                        self.report();
+                        return;
+                    };
+
+                    if !self.is_first(&update) {
+                        continue;
+                    }
+
+                    // the buy itself runs on its own task, this only decodes
+                    let _ = meteora_controller.transaction_handler(
+                        update.signature,
+                        update.transaction,
+                        update.meta,
+                        update.is_vote,
+                        update.slot,
+                    );
+                }
+                Some(tracker) = meteora_controller.next_bought() => {
+                    meteora_controller.track(tracker);
+                }
+                // disabled once every account source is gone
+                Some(update) = account_receiver.recv() => {
+                    meteora_controller.account_handler(update);
+                }
+                _ = exit_interval.tick() => {
+                    meteora_controller.check_exits();
+                }
+                _ = report_interval.tick() => {
+                    self.report();
+                    meteora_controller.report_pools();
+                }
+            }
+        }
+    }
+
+    fn is_first(&mut self, update: &TransactionUpdate) -> bool {
+        let now = Instant::now();
+
+        if let Some((winner, first_seen)) = self.seen.get(&update.signature) {
+            if *winner != update.source {
+                let lag = now.duration_since(*first_seen);
+                let stats = self.stats.entry(update.source.clone()).or_default();
+                stats.losses += 1;
+                stats.lag_micros += lag.as_micros();
+            }
+            return false;
+        }
+
+        if self.order.len() >= DEDUP_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen
+            .insert(update.signature, (update.source.clone(), now));
+        self.order.push_back(update.signature);
+        self.stats.entry(update.source.clone()).or_default().wins += 1;
+
+        true
+    }
+
+    fn report(&self) {
+        for (name, stats) in &self.stats {
+            let avg_lag_ms = if stats.losses > 0 {
+                stats.lag_micros as f64 / stats.losses as f64 / 1000.0
+            } else {
+                0.0
+            };
+            info!(
+                "geyser endpoint {}: won {} races, lost {} (avg {:.2} ms behind)",
+                name, stats.wins, stats.losses, avg_lag_ms
+            );
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use crate::config::GeyserReconnectConfig;
+use crate::geyser::{backoff_delay, Error, GeyserResult, TransactionSource, TransactionUpdate};
+use async_trait::async_trait;
+use futures::StreamExt;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{
+    RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::message::v0::LoadedAddresses;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::bs58;
+use solana_transaction_status::{
+    InnerInstruction, InnerInstructions, TransactionStatusMeta, TransactionTokenBalance,
+    UiInstruction, UiTransactionEncoding, UiTransactionStatusMeta, UiTransactionTokenBalance,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error, info, warn};
+
+// the logs notification usually arrives before the rpc node can serve the transaction
+const FETCH_ATTEMPTS: u32 = 10;
+const FETCH_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+// Fallback source: `logsSubscribe` on a program over `ws_rpc`, then `getTransaction` over `http_rpc`.
+// Slower than geyser, but works on any rpc node including a local test validator.
+pub struct WebsocketLogsSource {
+    name: String,
+    ws_url: String,
+    http_rpc: Arc<RpcClient>,
+    program_id: Pubkey,
+    // only fetch transactions whose logs contain one of these substrings
+    log_filters: Vec<String>,
+    reconnect: GeyserReconnectConfig,
+}
+
+impl WebsocketLogsSource {
+    pub fn new(
+        name: String,
+        ws_url: String,
+        http_url: String,
+        program_id: Pubkey,
+        log_filters: Vec<String>,
+        reconnect: GeyserReconnectConfig,
+    ) -> Self {
+        WebsocketLogsSource {
+            name,
+            ws_url,
+            http_rpc: Arc::new(RpcClient::new_with_commitment(
+                http_url,
+                CommitmentConfig::confirmed(),
+            )),
+            program_id,
+            log_filters,
+            reconnect,
+        }
+    }
+
+    fn matches(&self, logs: &[String]) -> bool {
+        self.log_filters.is_empty()
+            || logs
+                .iter()
+                .any(|log| self.log_filters.iter().any(|filter| log.contains(filter)))
+    }
+
+    // Subscribes once and forwards matching transactions until the socket dies.
+    // Returns true if at least one notification was received.
+    async fn subscribe(&self, sender: &UnboundedSender<TransactionUpdate>) -> GeyserResult<bool> {
+        let pubsub_client = PubsubClient::new(&self.ws_url)
+            .await
+            .map_err(|err| Error::Custom(err.to_string()))?;
+
+        let (mut stream, unsubscribe) = pubsub_client
+            .logs_subscribe(
+                RpcTransactionLogsFilter::Mentions(vec![self.program_id.to_string()]),
+                RpcTransactionLogsConfig {
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await
+            .map_err(|err| Error::Custom(err.to_string()))?;
+
+        info!("subscribed to {} logs on {}", self.program_id, self.ws_url);
+        let mut received_any = false;
+
+        while let Some(response) = stream.next().await {
+            received_any = true;
+            let logs = response.value;
+            if logs.err.is_some() || !self.matches(&logs.logs) {
+                continue;
+            }
+
+            let Ok(signature) = Signature::from_str(&logs.signature) else {
+                continue;
+            };
+
+            let http_rpc = self.http_rpc.clone();
+            let sender = sender.clone();
+            let name = self.name.clone();
+            let slot = response.context.slot;
+            tokio::spawn(async move {
+                match fetch_transaction(&http_rpc, name, signature, slot).await {
+                    Ok(update) => {
+                        let _ = sender.send(update);
+                    }
+                    Err(err) => error!("failed to fetch transaction {}: {:?}", signature, err),
+                }
+            });
+
+            if sender.is_closed() {
+                break;
+            }
+        }
+
+        unsubscribe().await;
+        Ok(received_any)
+    }
+}
+
+#[async_trait]
+impl TransactionSource for WebsocketLogsSource {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn consume(&self, sender: UnboundedSender<TransactionUpdate>) -> GeyserResult<()> {
+        let mut failures: u32 = 0;
+
+        loop {
+            if sender.is_closed() {
+                info!("transaction receiver dropped, stopping websocket {}", self.name);
+                return Ok(());
+            }
+
+            match self.subscribe(&sender).await {
+                Ok(true) => {
+                    warn!("websocket logs stream closed by {}", self.ws_url);
+                    failures = 0;
+                }
+                Ok(false) => warn!("websocket logs stream closed by {}", self.ws_url),
+                Err(err) => error!("websocket logs subscription failed: {:?}", err),
+            }
+
+            failures += 1;
+            let delay = backoff_delay(&self.reconnect, failures);
+            info!("reconnecting to websocket {} in {:?}", self.ws_url, delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+async fn fetch_transaction(
+    http_rpc: &RpcClient,
+    source: String,
+    signature: Signature,
+    slot: u64,
+) -> anyhow::Result<TransactionUpdate> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+
+    let mut attempt = 0;
+    let confirmed_transaction = loop {
+        attempt += 1;
+        match http_rpc.get_transaction_with_config(&signature, config).await {
+            Ok(transaction) => break transaction,
+            Err(err) if attempt < FETCH_ATTEMPTS => {
+                debug!("transaction {} not available yet: {:?}", signature, err);
+                tokio::time::sleep(FETCH_RETRY_DELAY).await;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+
+    let transaction = confirmed_transaction
+        .transaction
+        .transaction
+        .decode()
+        .ok_or_else(|| anyhow::anyhow!("cannot decode transaction {}", signature))?;
+    let meta = confirmed_transaction
+        .transaction
+        .meta
+        .ok_or_else(|| anyhow::anyhow!("transaction {} has no meta", signature))?;
+
+    Ok(TransactionUpdate {
+        source,
+        signature,
+        transaction,
+        meta: status_meta_from_ui(meta)?,
+        is_vote: false,
+        slot,
+    })
+}
+
+// Converts the json-rpc meta into the same shape geyser hands us, so the controller
+// doesn't care where a transaction came from. Parsed instructions are not requested
+// (base64 encoding), so every inner instruction is expected in compiled form.
+fn status_meta_from_ui(meta: UiTransactionStatusMeta) -> anyhow::Result<TransactionStatusMeta> {
+    let inner_instructions: Option<Vec<_>> = meta.inner_instructions.into();
+    let inner_instructions = inner_instructions
+        .map(|inner_instructions| {
+            inner_instructions
+                .into_iter()
+                .map(|inner| {
+                    let instructions = inner
+                        .instructions
+                        .into_iter()
+                        .filter_map(|instruction| match instruction {
+                            UiInstruction::Compiled(compiled) => Some(compiled),
+                            UiInstruction::Parsed(_) => None,
+                        })
+                        .map(|compiled| {
+                            Ok(InnerInstruction {
+                                instruction: CompiledInstruction {
+                                    program_id_index: compiled.program_id_index,
+                                    accounts: compiled.accounts,
+                                    data: bs58::decode(compiled.data).into_vec()?,
+                                },
+                                stack_height: compiled.stack_height,
+                            })
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    Ok(InnerInstructions {
+                        index: inner.index,
+                        instructions,
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        })
+        .transpose()?;
+
+    let loaded_addresses: Option<_> = meta.loaded_addresses.into();
+    let loaded_addresses = match loaded_addresses {
+        Some(ui_loaded_addresses) => LoadedAddresses {
+            writable: parse_pubkeys(&ui_loaded_addresses.writable)?,
+            readonly: parse_pubkeys(&ui_loaded_addresses.readonly)?,
+        },
+        None => LoadedAddresses::default(),
+    };
+
+    let pre_token_balances: Option<Vec<_>> = meta.pre_token_balances.into();
+    let post_token_balances: Option<Vec<_>> = meta.post_token_balances.into();
+
+    Ok(TransactionStatusMeta {
+        status: meta.status,
+        fee: meta.fee,
+        pre_balances: meta.pre_balances,
+        post_balances: meta.post_balances,
+        inner_instructions,
+        log_messages: meta.log_messages.into(),
+        pre_token_balances: pre_token_balances.map(token_balances_from_ui),
+        post_token_balances: post_token_balances.map(token_balances_from_ui),
+        rewards: meta.rewards.into(),
+        loaded_addresses,
+        return_data: None,
+        compute_units_consumed: meta.compute_units_consumed.into(),
+    })
+}
+
+fn token_balances_from_ui(balances: Vec<UiTransactionTokenBalance>) -> Vec<TransactionTokenBalance> {
+    balances
+        .into_iter()
+        .map(|balance| TransactionTokenBalance {
+            account_index: balance.account_index,
+            mint: balance.mint,
+            ui_token_amount: balance.ui_token_amount,
+            owner: Option::<String>::from(balance.owner).unwrap_or_default(),
+            program_id: Option::<String>::from(balance.program_id).unwrap_or_default(),
+        })
+        .collect()
+}
+
+fn parse_pubkeys(keys: &[String]) -> anyhow::Result<Vec<Pubkey>> {
+    keys.iter()
+        .map(|key| Pubkey::from_str(key).map_err(Into::into))
+        .collect()
+}
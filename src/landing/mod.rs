@@ -0,0 +1,299 @@
+use crate::config::LandingConfig;
+use crate::tx_senders::solana_rpc::TxMetrics;
+use crate::tx_senders::{BundleStatus, TxResult, TxSender};
+use futures::StreamExt;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSignatureSubscribeConfig;
+use solana_client::rpc_response::RpcSignatureResult;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LandingSource {
+    Geyser,
+    Websocket,
+    StatusPoll,
+    BundleStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Landing {
+    pub slot: u64,
+    // false when the transaction landed but failed, e.g. on slippage
+    pub success: bool,
+    pub source: LandingSource,
+}
+
+// how many of the geyser stream's latest signatures are remembered for `confirm`
+const RECENT_SIGNATURES: usize = 10_000;
+
+// Our signatures waiting for the geyser stream, and the stream's latest signatures: the
+// transaction of a fast sender can come through geyser before its relay has answered.
+#[derive(Default)]
+struct Sightings {
+    pending: HashMap<Signature, oneshot::Sender<Landing>>,
+    recent: HashMap<Signature, u64>,
+    order: VecDeque<Signature>,
+}
+
+// Follows sent signatures until they land, from whichever of the geyser stream, a ws_rpc
+// signature subscription and getSignatureStatuses polling reports them first.
+#[derive(Clone)]
+pub struct LandingTracker {
+    config: LandingConfig,
+    ws_rpc: String,
+    rpc_client: Arc<RpcClient>,
+    sightings: Arc<Mutex<Sightings>>,
+    // one websocket for every signature subscription, reconnected once it fails
+    pubsub: Arc<tokio::sync::Mutex<Option<Arc<PubsubClient>>>>,
+}
+
+impl LandingTracker {
+    pub fn new(config: LandingConfig, ws_rpc: String, rpc_client: Arc<RpcClient>) -> Self {
+        LandingTracker {
+            config,
+            ws_rpc,
+            rpc_client,
+            sightings: Arc::new(Mutex::new(Sightings::default())),
+            pubsub: Arc::new(tokio::sync::Mutex::new(None)),
+        }
+    }
+
+    pub async fn current_slot(&self) -> Option<u64> {
+        match self
+            .rpc_client
+            .get_slot_with_commitment(CommitmentConfig::processed())
+            .await
+        {
+            Ok(slot) => Some(slot),
+            Err(err) => {
+                warn!("cannot fetch current slot: {:?}", err);
+                None
+            }
+        }
+    }
+
+    // Called with every transaction the geyser stream delivers. Its filters include our own
+    // wallet, so our swaps come through it too.
+    pub fn observe(&self, signature: &Signature, slot: u64) {
+        let mut sightings = self.sightings.lock().unwrap();
+        if let Some(sender) = sightings.pending.remove(signature) {
+            let _ = sender.send(geyser_landing(slot));
+            return;
+        }
+
+        if sightings.order.len() >= RECENT_SIGNATURES {
+            if let Some(oldest) = sightings.order.pop_front() {
+                sightings.recent.remove(&oldest);
+            }
+        }
+        sightings.recent.insert(*signature, slot);
+        sightings.order.push_back(*signature);
+    }
+
+    // Follows whatever a sender answered until it lands.
+    pub async fn follow(&self, sender: &dyn TxSender, tx_result: &TxResult) -> Option<Landing> {
+        match tx_result {
+            TxResult::Signature(signature) => self.confirm(*signature).await,
+            // Jito answers with the signature when no bundle ID header came back
+            TxResult::BundleID(bundle_id) => match Signature::from_str(bundle_id) {
+                Ok(signature) => self.confirm(signature).await,
+                Err(_) => self.confirm_bundle(sender, bundle_id).await,
+            },
+        }
+    }
+
+    // None when the signature hasn't landed within the timeout.
+    pub async fn confirm(&self, signature: Signature) -> Option<Landing> {
+        let receiver = {
+            let mut sightings = self.sightings.lock().unwrap();
+            if let Some(slot) = sightings.recent.get(&signature) {
+                return Some(geyser_landing(*slot));
+            }
+            let (sender, receiver) = oneshot::channel();
+            sightings.pending.insert(signature, sender);
+            receiver
+        };
+
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        // a source that fails is disabled, the others keep going
+        let landing = tokio::select! {
+            Ok(landing) = receiver => Some(landing),
+            Some(landing) = self.subscribe(&signature) => Some(landing),
+            landing = self.poll(&signature) => Some(landing),
+            _ = tokio::time::sleep(timeout) => None,
+        };
+        self.sightings.lock().unwrap().pending.remove(&signature);
+
+        if let Some(landing) = &landing {
+            debug!("{} landed in slot {} ({:?})", signature, landing.slot, landing.source);
+        }
+        landing
+    }
+
+    // Polls the block engine that took the bundle until it lands, fails or is dropped.
+    pub async fn confirm_bundle(&self, sender: &dyn TxSender, bundle_id: &str) -> Option<Landing> {
+        let grace = Duration::from_millis(self.config.invalid_bundle_grace_ms);
+        let started = Instant::now();
+        let poll = async {
+            let mut interval =
+                tokio::time::interval(Duration::from_millis(self.config.poll_interval_ms.max(1)));
+            loop {
+                interval.tick().await;
+                match sender.bundle_status(bundle_id).await {
+                    Ok(BundleStatus::Landed(slot)) => {
+                        // bundles land atomically, a reverting transaction drops the bundle
+                        return Some(Landing {
+                            slot,
+                            success: true,
+                            source: LandingSource::BundleStatus,
+                        });
+                    }
+                    Ok(BundleStatus::Pending) => {}
+                    Ok(BundleStatus::Invalid) if started.elapsed() < grace => {
+                        debug!("bundle {} not indexed yet", bundle_id);
+                    }
+                    Ok(status) => {
+                        warn!("bundle {} from {}: {:?}", bundle_id, sender.name(), status);
+                        return None;
+                    }
+                    Err(err) => debug!("no status for bundle {}: {:?}", bundle_id, err),
+                }
+            }
+        };
+
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let landing = tokio::time::timeout(timeout, poll).await.ok().flatten();
+        if let Some(landing) = &landing {
+            debug!("bundle {} landed in slot {}", bundle_id, landing.slot);
+        }
+        landing
+    }
+
+    async fn pubsub(&self) -> Option<Arc<PubsubClient>> {
+        let mut pubsub = self.pubsub.lock().await;
+        if let Some(client) = pubsub.as_ref() {
+            return Some(client.clone());
+        }
+        match PubsubClient::new(&self.ws_rpc).await {
+            Ok(client) => {
+                let client = Arc::new(client);
+                *pubsub = Some(client.clone());
+                Some(client)
+            }
+            Err(err) => {
+                warn!("cannot connect to {}: {:?}", self.ws_rpc, err);
+                None
+            }
+        }
+    }
+
+    async fn subscribe(&self, signature: &Signature) -> Option<Landing> {
+        let client = self.pubsub().await?;
+        let config = RpcSignatureSubscribeConfig {
+            commitment: Some(CommitmentConfig::confirmed()),
+            enable_received_notification: Some(false),
+        };
+        let (mut stream, unsubscribe) = match client
+            .signature_subscribe(signature, Some(config))
+            .await
+        {
+            Ok(subscription) => subscription,
+            Err(err) => {
+                warn!("cannot subscribe to {}: {:?}", signature, err);
+                // most likely a dead connection, the next subscription opens a new one
+                *self.pubsub.lock().await = None;
+                return None;
+            }
+        };
+
+        let landing = loop {
+            let response = stream.next().await?;
+            if let RpcSignatureResult::ProcessedSignature(result) = response.value {
+                break Landing {
+                    slot: response.context.slot,
+                    success: result.err.is_none(),
+                    source: LandingSource::Websocket,
+                };
+            }
+        };
+        unsubscribe().await;
+        Some(landing)
+    }
+
+    async fn poll(&self, signature: &Signature) -> Landing {
+        let mut interval =
+            tokio::time::interval(Duration::from_millis(self.config.poll_interval_ms.max(1)));
+        loop {
+            interval.tick().await;
+            match self.rpc_client.get_signature_statuses(&[*signature]).await {
+                Ok(response) => {
+                    let Some(Some(status)) = response.value.into_iter().next() else {
+                        continue;
+                    };
+                    if status.satisfies_commitment(CommitmentConfig::confirmed()) {
+                        return Landing {
+                            slot: status.slot,
+                            success: status.err.is_none(),
+                            source: LandingSource::StatusPoll,
+                        };
+                    }
+                }
+                Err(err) => debug!("no status for {}: {:?}", signature, err),
+            }
+        }
+    }
+}
+
+// The stream only carries successful transactions.
+fn geyser_landing(slot: u64) -> Landing {
+    Landing {
+        slot,
+        success: true,
+        source: LandingSource::Geyser,
+    }
+}
+
+#[derive(Default, Debug)]
+struct SenderStats {
+    sent: u64,
+    landed: u64,
+    succeeded: u64,
+    // sum over landed sends
+    slot_latency: u64,
+}
+
+// Keeps per-sender landing totals over the session from the metrics of every send.
+pub async fn collect_metrics(mut receiver: mpsc::Receiver<TxMetrics>) {
+    let mut stats: HashMap<String, SenderStats> = HashMap::new();
+
+    while let Some(metrics) = receiver.recv().await {
+        debug!("tx metrics: {:?}", metrics);
+        let sender = stats.entry(metrics.rpc_name.clone()).or_default();
+        sender.sent += 1;
+        if metrics.slot_landed.is_some() {
+            sender.landed += 1;
+            sender.slot_latency += metrics.slot_latency.unwrap_or_default();
+        }
+        if metrics.success {
+            sender.succeeded += 1;
+        }
+
+        let avg_slot_latency = if sender.landed > 0 {
+            sender.slot_latency as f64 / sender.landed as f64
+        } else {
+            0.0
+        };
+        info!(
+            "sender {}: {} of {} sends landed ({} succeeded), avg {:.1} slots after send",
+            metrics.rpc_name, sender.landed, sender.sent, sender.succeeded, avg_slot_latency
+        );
+    }
+}
@@ -23,21 +23,39 @@ use tokio_util::sync::CancellationToken;
 use yellowstone_grpc_proto::geyser::{
     CommitmentLevel, SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions,
 };
-use tracing::info;
+use tracing::{error, info};
 use crate::bench::Bench;
-use crate::geyser::{GeyserResult, YellowstoneGrpcGeyser, YellowstoneGrpcGeyserClient};
+use crate::geyser::accounts::GeyserAccountSubscriber;
+use crate::geyser::multiplexer::GeyserMultiplexer;
+use crate::geyser::websocket::WebsocketLogsSource;
+use crate::geyser::{GeyserResult, TransactionSource, YellowstoneGrpcGeyserClient};
 use crate::config::PingThingsArgs;
 use crate::meteora::MeteoraController;
+use crate::dlmm::DLMM_PROGRAM_ID;
+use crate::damm_v2::DAMM_V2_PROGRAM_ID;
+use crate::dbc::DBC_PROGRAM_ID;
 
 pub const METEORA_PROGRAM_ID: Pubkey = pubkey!("Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB");
 pub const WSOL_ACCOUNT_ID: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
 
+mod activation;
 mod bench;
 mod config;
 mod tx_senders;
 mod geyser;
 mod core;
 mod meteora;
+mod positions;
+mod pricing;
+mod exits;
+mod landing;
+mod filters;
+mod safety;
+mod token;
+mod quote;
+mod dlmm;
+mod damm_v2;
+mod dbc;
 
 #[tokio::main]
 pub async fn main() -> GeyserResult<()> {
@@ -51,7 +69,8 @@ pub async fn main() -> GeyserResult<()> {
     let config_controller: PingThingsArgs = PingThingsArgs::new();
     let bench_controller: Bench = Bench::new(config_controller.clone());
 
-    let meteora_controller: MeteoraController = MeteoraController::new(config_controller.clone(), bench_controller.clone());
+    let mut meteora_controller: MeteoraController = MeteoraController::new(config_controller.clone(), bench_controller.clone());
+    meteora_controller.load_existing_positions().await;
 
     info!("starting with config {:?}", config_controller);
 
@@ -60,10 +79,27 @@ pub async fn main() -> GeyserResult<()> {
 
     let account_filters: HashMap<String, SubscribeRequestFilterAccounts> = HashMap::new();
 
+    let mut watched_programs: Vec<Pubkey> = Vec::new();
+    if config_controller.programs.dynamic_amm {
+        watched_programs.push(METEORA_PROGRAM_ID);
+    }
+    if config_controller.programs.dlmm {
+        watched_programs.push(DLMM_PROGRAM_ID);
+    }
+    if config_controller.programs.damm_v2 {
+        watched_programs.push(DAMM_V2_PROGRAM_ID);
+    }
+    if config_controller.programs.dbc {
+        watched_programs.push(DBC_PROGRAM_ID);
+    }
+
     let transaction_filter = SubscribeRequestFilterTransactions {
         vote: Some(false),
         failed: Some(false),
-        account_include: vec![METEORA_PROGRAM_ID.to_string().clone()],
+        account_include: watched_programs
+            .iter()
+            .map(|program_id| program_id.to_string())
+            .collect(),
         account_exclude: vec![],
         account_required: vec![
             WSOL_ACCOUNT_ID.to_string().clone()
@@ -76,15 +112,85 @@ pub async fn main() -> GeyserResult<()> {
 
     transaction_filters.insert("meteora_transaction_filter".to_string(), transaction_filter);
 
-    let yellowstone_grpc = YellowstoneGrpcGeyserClient::new(
-        config_controller.geyser_url,
-        Some(config_controller.geyser_x_token),
-        Some(CommitmentLevel::Processed),
-        account_filters,
-        transaction_filters,
-        Arc::new(RwLock::new(HashSet::new())),
+    // our own swaps, for landing tracking: a prefunded dynamic AMM swap doesn't list the WSOL
+    // mint the filter above requires
+    let owner = Keypair::from_base58_string(config_controller.private_key.as_str()).pubkey();
+    transaction_filters.insert(
+        "own_transaction_filter".to_string(),
+        SubscribeRequestFilterTransactions {
+            vote: Some(false),
+            failed: Some(false),
+            account_include: vec![owner.to_string()],
+            account_exclude: vec![],
+            account_required: vec![],
+            signature: None,
+        },
     );
 
-    let _ = yellowstone_grpc.consume(meteora_controller).await;
+    let mut sources: Vec<Arc<dyn TransactionSource>> = Vec::new();
+
+    for (name, endpoint) in config_controller.geyser_endpoints() {
+        sources.push(Arc::new(YellowstoneGrpcGeyserClient::new(
+            name,
+            endpoint.url,
+            endpoint.x_token,
+            Some(CommitmentLevel::Processed),
+            account_filters.clone(),
+            transaction_filters.clone(),
+            Arc::new(RwLock::new(HashSet::new())),
+            config_controller.geyser_reconnect.clone(),
+        )));
+    }
+
+    if config_controller.websocket_source.enabled {
+        // logsSubscribe only accepts a single mentioned address, one source per program
+        for program_id in &watched_programs {
+            sources.push(Arc::new(WebsocketLogsSource::new(
+                format!("websocket-{}", program_id),
+                config_controller.ws_rpc.clone(),
+                config_controller.http_rpc.clone(),
+                *program_id,
+                config_controller.websocket_source.log_filters.clone(),
+                config_controller.geyser_reconnect.clone(),
+            )));
+        }
+    }
+
+    let (update_sender, update_receiver) = tokio::sync::mpsc::unbounded_channel();
+
+    for source in sources {
+        let sender = update_sender.clone();
+        tokio::spawn(async move {
+            if let Err(err) = source.consume(sender).await {
+                error!("transaction source {} stopped: {:?}", source.name(), err);
+            }
+        });
+    }
+    drop(update_sender);
+
+    // reserve accounts of the pools we hold, they keep the pool snapshots current
+    let (account_sender, account_receiver) = tokio::sync::mpsc::unbounded_channel();
+
+    for (name, endpoint) in config_controller.geyser_endpoints() {
+        let subscriber = GeyserAccountSubscriber::new(
+            format!("{}-accounts", name),
+            endpoint.url,
+            endpoint.x_token,
+            Some(CommitmentLevel::Processed),
+            meteora_controller.watched_accounts(),
+            config_controller.geyser_reconnect.clone(),
+        );
+        let sender = account_sender.clone();
+        tokio::spawn(async move {
+            if let Err(err) = subscriber.consume(sender).await {
+                error!("account source {} stopped: {:?}", subscriber.name, err);
+            }
+        });
+    }
+    drop(account_sender);
+
+    GeyserMultiplexer::new()
+        .run(update_receiver, account_receiver, meteora_controller)
+        .await;
     Ok(())
 }
@@ -0,0 +1,244 @@
+use crate::core::decode::{account, DecodeError};
+use crate::meteora::instructions::{CurveType, PoolFees};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::pubkey::Pubkey;
+
+// Anchor account discriminators of the dynamic AMM program: sha256("account:<AccountName>")[..8]
+pub const CONFIG_ACCOUNT_DISC: [u8; 8] = [155, 12, 170, 224, 30, 250, 204, 130];
+pub const POOL_ACCOUNT_DISC: [u8; 8] = [241, 154, 109, 4, 17, 177, 109, 188];
+// owned by the dynamic vault program, anchor discriminators only depend on the name
+pub const VAULT_ACCOUNT_DISC: [u8; 8] = [211, 8, 232, 43, 2, 152, 117, 119];
+
+// locked profit degrades over 1e12
+pub const LOCKED_PROFIT_DEGRADATION_DENOMINATOR: u128 = 1_000_000_000_000;
+
+// Pool config shared by every pool created with it; only the leading fields are decoded.
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct Config {
+    pub pool_fees: PoolFees,
+    // pools created with this config activate this many slots / seconds after creation
+    pub activation_duration: u64,
+    pub vault_config_key: Pubkey,
+    pub pool_creator_authority: Pubkey,
+    // 0 = slot, 1 = timestamp
+    pub activation_type: u8,
+    pub partner_fee_numerator: u64,
+}
+
+impl Config {
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        account("Config", CONFIG_ACCOUNT_DISC, data)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub enum PoolType {
+    Permissioned,
+    Permissionless,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct Bootstrapping {
+    pub activation_point: u64,
+    pub whitelisted_vault: Pubkey,
+    pub pool_creator: Pubkey,
+    // 0 = slot, 1 = timestamp
+    pub activation_type: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct PartnerInfo {
+    pub fee_numerator: u64,
+    pub partner_authority: Pubkey,
+    pub pending_fee_a: u64,
+    pub pending_fee_b: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct Padding {
+    pub padding_0: [u8; 6],
+    pub padding_1: [u64; 21],
+    pub padding_2: [u64; 21],
+}
+
+// A dynamic AMM pool. Its reserves are vault LP tokens, see `Vault::amount_by_share`.
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct Pool {
+    pub lp_mint: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub a_vault: Pubkey,
+    pub b_vault: Pubkey,
+    pub a_vault_lp: Pubkey,
+    pub b_vault_lp: Pubkey,
+    pub a_vault_lp_bump: u8,
+    pub enabled: bool,
+    pub protocol_token_a_fee: Pubkey,
+    pub protocol_token_b_fee: Pubkey,
+    pub fee_last_updated_at: u64,
+    pub padding0: [u8; 24],
+    pub fees: PoolFees,
+    pub pool_type: PoolType,
+    pub stake: Pubkey,
+    pub total_locked_lp: u64,
+    pub bootstrapping: Bootstrapping,
+    pub partner_info: PartnerInfo,
+    pub padding: Padding,
+    pub curve_type: CurveType,
+}
+
+impl Pool {
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        account("Pool", POOL_ACCOUNT_DISC, data)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct VaultBumps {
+    pub vault_bump: u8,
+    pub token_vault_bump: u8,
+}
+
+// Profit reported by the vault's strategies unlocks linearly, it is not withdrawable before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct LockedProfitTracker {
+    pub last_updated_locked_profit: u64,
+    pub last_report: u64,
+    pub locked_profit_degradation: u64,
+}
+
+impl LockedProfitTracker {
+    pub fn locked_profit(&self, current_time: u64) -> u64 {
+        let elapsed = current_time.saturating_sub(self.last_report) as u128;
+        let locked_fund_ratio = elapsed * self.locked_profit_degradation as u128;
+        if locked_fund_ratio > LOCKED_PROFIT_DEGRADATION_DENOMINATOR {
+            return 0;
+        }
+        (self.last_updated_locked_profit as u128
+            * (LOCKED_PROFIT_DEGRADATION_DENOMINATOR - locked_fund_ratio)
+            / LOCKED_PROFIT_DEGRADATION_DENOMINATOR) as u64
+    }
+}
+
+// A dynamic vault holding one token for every pool that trades it.
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct Vault {
+    pub enabled: u8,
+    pub bumps: VaultBumps,
+    pub total_amount: u64,
+    pub token_vault: Pubkey,
+    pub fee_vault: Pubkey,
+    pub token_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub strategies: [Pubkey; 30],
+    pub base: Pubkey,
+    pub admin: Pubkey,
+    pub operator: Pubkey,
+    pub locked_profit_tracker: LockedProfitTracker,
+}
+
+impl Vault {
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        account("Vault", VAULT_ACCOUNT_DISC, data)
+    }
+
+    // what the vault holds minus profit that is still locked, `current_time` in unix seconds
+    pub fn unlocked_amount(&self, current_time: u64) -> u64 {
+        self.total_amount
+            .saturating_sub(self.locked_profit_tracker.locked_profit(current_time))
+    }
+
+    // tokens one vault LP token is worth, the price pools trade the vault LP at; Q64.64
+    pub fn virtual_price(&self, current_time: u64, lp_supply: u64) -> Option<u128> {
+        if lp_supply == 0 {
+            return None;
+        }
+        Some(((self.unlocked_amount(current_time) as u128) << 64) / lp_supply as u128)
+    }
+
+    // tokens `share` vault LP tokens are worth, rounded down with the virtual price
+    pub fn amount_by_share(&self, current_time: u64, share: u64, lp_supply: u64) -> Option<u64> {
+        let virtual_price = self.virtual_price(current_time, lp_supply)?;
+        let whole = share as u128 * (virtual_price >> 64);
+        let fraction = (share as u128 * (virtual_price & u64::MAX as u128)) >> 64;
+        u64::try_from(whole + fraction).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1 << 64;
+
+    // 1_000 of profit reported at t = 100 that unlocks over 100 seconds
+    fn vault(total_amount: u64) -> Vault {
+        Vault {
+            enabled: 1,
+            bumps: VaultBumps {
+                vault_bump: 255,
+                token_vault_bump: 255,
+            },
+            total_amount,
+            token_vault: Pubkey::new_unique(),
+            fee_vault: Pubkey::new_unique(),
+            token_mint: Pubkey::new_unique(),
+            lp_mint: Pubkey::new_unique(),
+            strategies: [Pubkey::default(); 30],
+            base: Pubkey::new_unique(),
+            admin: Pubkey::new_unique(),
+            operator: Pubkey::new_unique(),
+            locked_profit_tracker: LockedProfitTracker {
+                last_updated_locked_profit: 1_000,
+                last_report: 100,
+                locked_profit_degradation: (LOCKED_PROFIT_DEGRADATION_DENOMINATOR / 100) as u64,
+            },
+        }
+    }
+
+    #[test]
+    fn locked_profit_degrades_linearly() {
+        let tracker = vault(0).locked_profit_tracker;
+        // the clock can lag behind the report
+        assert_eq!(tracker.locked_profit(50), 1_000);
+        assert_eq!(tracker.locked_profit(100), 1_000);
+        assert_eq!(tracker.locked_profit(125), 750);
+        assert_eq!(tracker.locked_profit(150), 500);
+        assert_eq!(tracker.locked_profit(199), 10);
+        assert_eq!(tracker.locked_profit(200), 0);
+        assert_eq!(tracker.locked_profit(u64::MAX), 0);
+    }
+
+    #[test]
+    fn virtual_price_excludes_locked_profit() {
+        let vault = vault(9_000);
+        assert_eq!(vault.unlocked_amount(100), 8_000);
+        assert_eq!(vault.virtual_price(100, 8_000), Some(ONE));
+        assert_eq!(vault.virtual_price(150, 8_000), Some(ONE + ONE / 16));
+        assert_eq!(vault.virtual_price(200, 8_000), Some(ONE + ONE / 8));
+        assert_eq!(vault.virtual_price(200, 0), None);
+    }
+
+    #[test]
+    fn amount_by_share() {
+        let vault = vault(9_000);
+        assert_eq!(vault.amount_by_share(100, 4_000, 8_000), Some(4_000));
+        assert_eq!(vault.amount_by_share(150, 4_000, 8_000), Some(4_250));
+        assert_eq!(vault.amount_by_share(200, 4_000, 8_000), Some(4_500));
+        assert_eq!(vault.amount_by_share(200, 8_000, 8_000), Some(9_000));
+        // 9_000 / 7 per LP token
+        assert_eq!(vault.amount_by_share(200, 3, 7), Some(3_857));
+        assert_eq!(vault.amount_by_share(200, 4_000, 0), None);
+    }
+
+    #[test]
+    fn amount_by_share_of_large_vaults() {
+        let mut vault = vault(u64::MAX);
+        vault.locked_profit_tracker.last_updated_locked_profit = 0;
+        assert_eq!(vault.amount_by_share(0, 1, 1), Some(u64::MAX));
+        assert_eq!(vault.amount_by_share(0, u64::MAX, u64::MAX), Some(u64::MAX));
+        assert_eq!(vault.amount_by_share(0, u64::MAX / 2, u64::MAX), Some(u64::MAX / 2));
+        // worth more than a u64 can hold
+        assert_eq!(vault.amount_by_share(0, 2, 1), None);
+    }
+}
@@ -0,0 +1,890 @@
+use crate::core::decode::{args, instruction_accounts, split_discriminator, DecodeError};
+use crate::meteora::AddLiquidityIxData;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+
+// Anchor discriminators of the dynamic AMM program: sha256("global:<instruction_name>")[..8]
+pub const INITIALIZE_PERMISSIONED_POOL_DISC: [u8; 8] = [77, 85, 178, 157, 50, 48, 212, 126];
+pub const INITIALIZE_PERMISSIONLESS_POOL_DISC: [u8; 8] = [118, 173, 41, 157, 173, 72, 97, 103];
+pub const INITIALIZE_PERMISSIONLESS_POOL_WITH_FEE_TIER_DISC: [u8; 8] =
+    [6, 135, 68, 147, 229, 82, 169, 113];
+pub const ENABLE_OR_DISABLE_POOL_DISC: [u8; 8] = [128, 6, 228, 131, 55, 161, 52, 169];
+pub const SWAP_DISC: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+pub const REMOVE_LIQUIDITY_SINGLE_SIDE_DISC: [u8; 8] = [84, 84, 177, 66, 254, 185, 10, 251];
+pub const ADD_IMBALANCE_LIQUIDITY_DISC: [u8; 8] = [79, 35, 122, 84, 173, 15, 93, 191];
+pub const REMOVE_BALANCE_LIQUIDITY_DISC: [u8; 8] = [133, 109, 44, 179, 56, 238, 114, 33];
+pub const ADD_BALANCE_LIQUIDITY_DISC: [u8; 8] = [168, 227, 50, 62, 189, 171, 84, 176];
+pub const SET_POOL_FEES_DISC: [u8; 8] = [102, 44, 158, 54, 205, 37, 126, 78];
+pub const OVERRIDE_CURVE_PARAM_DISC: [u8; 8] = [98, 86, 204, 51, 94, 71, 69, 187];
+pub const GET_POOL_INFO_DISC: [u8; 8] = [9, 48, 220, 101, 22, 240, 78, 200];
+pub const BOOTSTRAP_LIQUIDITY_DISC: [u8; 8] = [4, 228, 215, 71, 225, 253, 119, 206];
+pub const CREATE_MINT_METADATA_DISC: [u8; 8] = [13, 70, 168, 41, 250, 100, 148, 90];
+pub const CREATE_LOCK_ESCROW_DISC: [u8; 8] = [54, 87, 165, 19, 69, 227, 218, 224];
+pub const LOCK_DISC: [u8; 8] = [21, 19, 208, 43, 237, 62, 255, 87];
+pub const CLAIM_FEE_DISC: [u8; 8] = [169, 32, 79, 137, 136, 232, 70, 137];
+pub const CREATE_CONFIG_DISC: [u8; 8] = [201, 207, 243, 114, 75, 111, 47, 189];
+pub const CLOSE_CONFIG_DISC: [u8; 8] = [145, 9, 72, 157, 95, 125, 61, 85];
+pub const INITIALIZE_PERMISSIONLESS_CONSTANT_PRODUCT_POOL_WITH_CONFIG_DISC: [u8; 8] =
+    [7, 166, 138, 171, 206, 171, 236, 244];
+pub const INITIALIZE_PERMISSIONLESS_CONSTANT_PRODUCT_POOL_WITH_CONFIG_2_DISC: [u8; 8] =
+    [48, 149, 220, 130, 61, 11, 9, 178];
+pub const INITIALIZE_CUSTOMIZABLE_PERMISSIONLESS_CONSTANT_PRODUCT_POOL_DISC: [u8; 8] =
+    [145, 24, 172, 194, 219, 125, 3, 190];
+pub const UPDATE_ACTIVATION_POINT_DISC: [u8; 8] = [150, 62, 125, 219, 171, 220, 26, 237];
+pub const WITHDRAW_PROTOCOL_FEES_DISC: [u8; 8] = [11, 68, 165, 98, 18, 208, 134, 73];
+pub const SET_WHITELISTED_VAULT_DISC: [u8; 8] = [12, 148, 94, 42, 55, 57, 83, 247];
+pub const PARTNER_CLAIM_FEE_DISC: [u8; 8] = [57, 53, 176, 30, 123, 70, 52, 64];
+
+instruction_accounts!(InitializePermissionedPoolAccounts {
+    pool, lp_mint, token_a_mint, token_b_mint, a_vault, b_vault, a_vault_lp_mint, b_vault_lp_mint,
+    a_vault_lp, b_vault_lp, admin_token_a, admin_token_b, admin_pool_lp, protocol_token_a_fee,
+    protocol_token_b_fee, admin, fee_owner, rent, mint_metadata, metadata_program, vault_program,
+    token_program, associated_token_program, system_program,
+});
+
+instruction_accounts!(InitializePermissionlessPoolAccounts {
+    pool, lp_mint, token_a_mint, token_b_mint, a_vault, b_vault, a_token_vault, b_token_vault,
+    a_vault_lp_mint, b_vault_lp_mint, a_vault_lp, b_vault_lp, payer_token_a, payer_token_b,
+    payer_pool_lp, protocol_token_a_fee, protocol_token_b_fee, payer, fee_owner, rent,
+    mint_metadata, metadata_program, vault_program, token_program, associated_token_program,
+    system_program,
+});
+
+// shared by `initialize_permissionless_constant_product_pool_with_config` and `..._with_config2`
+instruction_accounts!(InitializePermissionlessPoolWithConfigAccounts {
+    pool, config, lp_mint, token_a_mint, token_b_mint, a_vault, b_vault, a_token_vault,
+    b_token_vault, a_vault_lp_mint, b_vault_lp_mint, a_vault_lp, b_vault_lp, payer_token_a,
+    payer_token_b, payer_pool_lp, protocol_token_a_fee, protocol_token_b_fee, payer, rent,
+    mint_metadata, metadata_program, vault_program, token_program, associated_token_program,
+    system_program,
+});
+
+instruction_accounts!(InitializeCustomizablePermissionlessPoolAccounts {
+    pool, lp_mint, token_a_mint, token_b_mint, a_vault, b_vault, a_token_vault, b_token_vault,
+    a_vault_lp_mint, b_vault_lp_mint, a_vault_lp, b_vault_lp, payer_token_a, payer_token_b,
+    payer_pool_lp, protocol_token_a_fee, protocol_token_b_fee, payer, rent, mint_metadata,
+    metadata_program, vault_program, token_program, associated_token_program, system_program,
+});
+
+instruction_accounts!(SwapAccounts {
+    pool, user_source_token, user_destination_token, a_vault, b_vault, a_token_vault,
+    b_token_vault, a_vault_lp_mint, b_vault_lp_mint, a_vault_lp, b_vault_lp, protocol_token_fee,
+    user, vault_program, token_program,
+});
+
+// shared by add_balance / add_imbalance / remove_balance liquidity and bootstrap_liquidity
+instruction_accounts!(LiquidityAccounts {
+    pool, lp_mint, user_pool_lp, a_vault_lp, b_vault_lp, a_vault, b_vault, a_vault_lp_mint,
+    b_vault_lp_mint, a_token_vault, b_token_vault, user_a_token, user_b_token, user,
+    vault_program, token_program,
+});
+
+instruction_accounts!(RemoveLiquiditySingleSideAccounts {
+    pool, lp_mint, user_pool_lp, a_vault_lp, b_vault_lp, a_vault, b_vault, a_vault_lp_mint,
+    b_vault_lp_mint, a_token_vault, b_token_vault, user_destination_token, user, vault_program,
+    token_program,
+});
+
+instruction_accounts!(CreateLockEscrowAccounts {
+    pool, lock_escrow, owner, lp_mint, payer, system_program,
+});
+
+instruction_accounts!(LockAccounts {
+    pool, lp_mint, lock_escrow, owner, source_tokens, escrow_vault, token_program, a_vault,
+    b_vault, a_vault_lp, b_vault_lp, a_vault_lp_mint, b_vault_lp_mint,
+});
+
+instruction_accounts!(ClaimFeeAccounts {
+    pool, lp_mint, lock_escrow, owner, source_tokens, a_vault, b_vault, a_vault_lp, b_vault_lp,
+    a_vault_lp_mint, b_vault_lp_mint, user_a_token, user_b_token, escrow_vault, token_program,
+    a_token_vault, b_token_vault, vault_program,
+});
+
+// admin instructions that only touch the pool (enable/disable, override curve, activation point, ...)
+instruction_accounts!(PoolAdminAccounts { pool, admin });
+
+instruction_accounts!(SetPoolFeesAccounts { pool, fee_operator });
+
+instruction_accounts!(GetPoolInfoAccounts {
+    pool, lp_mint, a_vault_lp, b_vault_lp, a_vault, b_vault, a_vault_lp_mint, b_vault_lp_mint,
+});
+
+instruction_accounts!(CreateMintMetadataAccounts {
+    pool, lp_mint, a_vault_lp, mint_metadata, metadata_program, system_program, payer,
+});
+
+instruction_accounts!(CreateConfigAccounts { config, admin, system_program });
+
+instruction_accounts!(CloseConfigAccounts { config, admin, rent_receiver });
+
+instruction_accounts!(WithdrawProtocolFeesAccounts {
+    pool, a_vault_lp, protocol_token_a_fee, protocol_token_b_fee, treasury_token_a,
+    treasury_token_b, token_program,
+});
+
+instruction_accounts!(PartnerClaimFeeAccounts {
+    pool, a_vault_lp, protocol_token_a_fee, protocol_token_b_fee, partner_token_a,
+    partner_token_b, token_program, partner_authority,
+});
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub enum DepegType {
+    None,
+    Marinade,
+    Lido,
+    SplStake,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct Depeg {
+    pub base_virtual_price: u64,
+    pub base_cache_updated: u64,
+    pub depeg_type: DepegType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct TokenMultiplier {
+    pub token_a_multiplier: u64,
+    pub token_b_multiplier: u64,
+    pub precision_factor: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub enum CurveType {
+    ConstantProduct,
+    Stable {
+        amp: u64,
+        token_multiplier: TokenMultiplier,
+        depeg: Depeg,
+        last_amp_updated_timestamp: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct PoolFees {
+    pub trade_fee_numerator: u64,
+    pub trade_fee_denominator: u64,
+    pub protocol_trade_fee_numerator: u64,
+    pub protocol_trade_fee_denominator: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct ConfigParameters {
+    pub trade_fee_numerator: u64,
+    pub protocol_trade_fee_numerator: u64,
+    pub activation_duration: u64,
+    pub vault_config_key: Pubkey,
+    pub pool_creator_authority: Pubkey,
+    pub activation_type: u8,
+    pub index: u64,
+    pub partner_fee_numerator: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct CustomizableParams {
+    // denominator is 100_000
+    pub trade_fee_numerator: u32,
+    pub activation_point: Option<u64>,
+    pub has_alpha_vault: bool,
+    // 0 = slot, 1 = timestamp
+    pub activation_type: u8,
+    pub padding: [u8; 90],
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct InitializePermissionlessPoolArgs {
+    pub curve_type: CurveType,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct InitializePermissionlessPoolWithFeeTierArgs {
+    pub curve_type: CurveType,
+    pub trade_fee_bps: u64,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct InitializePoolWithConfigArgs {
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct InitializeCustomizablePoolArgs {
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+    pub params: CustomizableParams,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct SwapArgs {
+    pub in_amount: u64,
+    pub minimum_out_amount: u64,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct RemoveLiquiditySingleSideArgs {
+    pub pool_token_amount: u64,
+    pub minimum_out_amount: u64,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct AddImbalanceLiquidityArgs {
+    pub minimum_pool_token_amount: u64,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct RemoveBalanceLiquidityArgs {
+    pub pool_token_amount: u64,
+    pub minimum_a_token_out: u64,
+    pub minimum_b_token_out: u64,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct AddBalanceLiquidityArgs {
+    pub pool_token_amount: u64,
+    pub maximum_token_a_amount: u64,
+    pub maximum_token_b_amount: u64,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct SetPoolFeesArgs {
+    pub fees: PoolFees,
+    pub new_partner_fee_numerator: u64,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct BootstrapLiquidityArgs {
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
+pub struct PartnerClaimFeeArgs {
+    pub max_amount_a: u64,
+    pub max_amount_b: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum MeteoraInstruction {
+    InitializePermissionedPool {
+        accounts: InitializePermissionedPoolAccounts,
+        curve_type: CurveType,
+    },
+    InitializePermissionlessPool {
+        accounts: InitializePermissionlessPoolAccounts,
+        args: InitializePermissionlessPoolArgs,
+    },
+    InitializePermissionlessPoolWithFeeTier {
+        accounts: InitializePermissionlessPoolAccounts,
+        args: InitializePermissionlessPoolWithFeeTierArgs,
+    },
+    InitializePermissionlessConstantProductPoolWithConfig {
+        accounts: InitializePermissionlessPoolWithConfigAccounts,
+        args: InitializePoolWithConfigArgs,
+    },
+    InitializePermissionlessConstantProductPoolWithConfig2 {
+        accounts: InitializePermissionlessPoolWithConfigAccounts,
+        args: AddLiquidityIxData,
+    },
+    InitializeCustomizablePermissionlessConstantProductPool {
+        accounts: InitializeCustomizablePermissionlessPoolAccounts,
+        args: InitializeCustomizablePoolArgs,
+    },
+    EnableOrDisablePool {
+        accounts: PoolAdminAccounts,
+        enable: bool,
+    },
+    Swap {
+        accounts: SwapAccounts,
+        args: SwapArgs,
+    },
+    RemoveLiquiditySingleSide {
+        accounts: RemoveLiquiditySingleSideAccounts,
+        args: RemoveLiquiditySingleSideArgs,
+    },
+    AddImbalanceLiquidity {
+        accounts: LiquidityAccounts,
+        args: AddImbalanceLiquidityArgs,
+    },
+    RemoveBalanceLiquidity {
+        accounts: LiquidityAccounts,
+        args: RemoveBalanceLiquidityArgs,
+    },
+    AddBalanceLiquidity {
+        accounts: LiquidityAccounts,
+        args: AddBalanceLiquidityArgs,
+    },
+    SetPoolFees {
+        accounts: SetPoolFeesAccounts,
+        args: SetPoolFeesArgs,
+    },
+    OverrideCurveParam {
+        accounts: PoolAdminAccounts,
+        curve_type: CurveType,
+    },
+    GetPoolInfo {
+        accounts: GetPoolInfoAccounts,
+    },
+    BootstrapLiquidity {
+        accounts: LiquidityAccounts,
+        args: BootstrapLiquidityArgs,
+    },
+    CreateMintMetadata {
+        accounts: CreateMintMetadataAccounts,
+    },
+    CreateLockEscrow {
+        accounts: CreateLockEscrowAccounts,
+    },
+    Lock {
+        accounts: LockAccounts,
+        max_amount: u64,
+    },
+    ClaimFee {
+        accounts: ClaimFeeAccounts,
+        max_amount: u64,
+    },
+    CreateConfig {
+        accounts: CreateConfigAccounts,
+        config_parameters: ConfigParameters,
+    },
+    CloseConfig {
+        accounts: CloseConfigAccounts,
+    },
+    UpdateActivationPoint {
+        accounts: PoolAdminAccounts,
+        new_activation_point: u64,
+    },
+    WithdrawProtocolFees {
+        accounts: WithdrawProtocolFeesAccounts,
+    },
+    SetWhitelistedVault {
+        accounts: PoolAdminAccounts,
+        whitelisted_vault: Pubkey,
+    },
+    PartnerClaimFee {
+        accounts: PartnerClaimFeeAccounts,
+        args: PartnerClaimFeeArgs,
+    },
+}
+
+impl MeteoraInstruction {
+    // Decodes a dynamic AMM instruction. The caller is expected to have checked the program id.
+    pub fn decode(instruction: &Instruction) -> Result<Self, DecodeError> {
+        let data = instruction.data.as_slice();
+        let accounts = instruction.accounts.as_slice();
+
+        let (discriminator, data) = split_discriminator(data)?;
+
+        let decoded = match discriminator {
+            INITIALIZE_PERMISSIONED_POOL_DISC => {
+                let name = "initialize_permissioned_pool";
+                MeteoraInstruction::InitializePermissionedPool {
+                    accounts: InitializePermissionedPoolAccounts::from_accounts(name, accounts)?,
+                    curve_type: args(name, data)?,
+                }
+            }
+            INITIALIZE_PERMISSIONLESS_POOL_DISC => {
+                let name = "initialize_permissionless_pool";
+                MeteoraInstruction::InitializePermissionlessPool {
+                    accounts: InitializePermissionlessPoolAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            INITIALIZE_PERMISSIONLESS_POOL_WITH_FEE_TIER_DISC => {
+                let name = "initialize_permissionless_pool_with_fee_tier";
+                MeteoraInstruction::InitializePermissionlessPoolWithFeeTier {
+                    accounts: InitializePermissionlessPoolAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            INITIALIZE_PERMISSIONLESS_CONSTANT_PRODUCT_POOL_WITH_CONFIG_DISC => {
+                let name = "initialize_permissionless_constant_product_pool_with_config";
+                MeteoraInstruction::InitializePermissionlessConstantProductPoolWithConfig {
+                    accounts: InitializePermissionlessPoolWithConfigAccounts::from_accounts(
+                        name, accounts,
+                    )?,
+                    args: args(name, data)?,
+                }
+            }
+            INITIALIZE_PERMISSIONLESS_CONSTANT_PRODUCT_POOL_WITH_CONFIG_2_DISC => {
+                let name = "initialize_permissionless_constant_product_pool_with_config2";
+                MeteoraInstruction::InitializePermissionlessConstantProductPoolWithConfig2 {
+                    accounts: InitializePermissionlessPoolWithConfigAccounts::from_accounts(
+                        name, accounts,
+                    )?,
+                    args: args(name, data)?,
+                }
+            }
+            INITIALIZE_CUSTOMIZABLE_PERMISSIONLESS_CONSTANT_PRODUCT_POOL_DISC => {
+                let name = "initialize_customizable_permissionless_constant_product_pool";
+                MeteoraInstruction::InitializeCustomizablePermissionlessConstantProductPool {
+                    accounts: InitializeCustomizablePermissionlessPoolAccounts::from_accounts(
+                        name, accounts,
+                    )?,
+                    args: args(name, data)?,
+                }
+            }
+            ENABLE_OR_DISABLE_POOL_DISC => {
+                let name = "enable_or_disable_pool";
+                MeteoraInstruction::EnableOrDisablePool {
+                    accounts: PoolAdminAccounts::from_accounts(name, accounts)?,
+                    enable: args(name, data)?,
+                }
+            }
+            SWAP_DISC => {
+                let name = "swap";
+                MeteoraInstruction::Swap {
+                    accounts: SwapAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            REMOVE_LIQUIDITY_SINGLE_SIDE_DISC => {
+                let name = "remove_liquidity_single_side";
+                MeteoraInstruction::RemoveLiquiditySingleSide {
+                    accounts: RemoveLiquiditySingleSideAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            ADD_IMBALANCE_LIQUIDITY_DISC => {
+                let name = "add_imbalance_liquidity";
+                MeteoraInstruction::AddImbalanceLiquidity {
+                    accounts: LiquidityAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            REMOVE_BALANCE_LIQUIDITY_DISC => {
+                let name = "remove_balance_liquidity";
+                MeteoraInstruction::RemoveBalanceLiquidity {
+                    accounts: LiquidityAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            ADD_BALANCE_LIQUIDITY_DISC => {
+                let name = "add_balance_liquidity";
+                MeteoraInstruction::AddBalanceLiquidity {
+                    accounts: LiquidityAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            SET_POOL_FEES_DISC => {
+                let name = "set_pool_fees";
+                MeteoraInstruction::SetPoolFees {
+                    accounts: SetPoolFeesAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            OVERRIDE_CURVE_PARAM_DISC => {
+                let name = "override_curve_param";
+                MeteoraInstruction::OverrideCurveParam {
+                    accounts: PoolAdminAccounts::from_accounts(name, accounts)?,
+                    curve_type: args(name, data)?,
+                }
+            }
+            GET_POOL_INFO_DISC => MeteoraInstruction::GetPoolInfo {
+                accounts: GetPoolInfoAccounts::from_accounts("get_pool_info", accounts)?,
+            },
+            BOOTSTRAP_LIQUIDITY_DISC => {
+                let name = "bootstrap_liquidity";
+                MeteoraInstruction::BootstrapLiquidity {
+                    accounts: LiquidityAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            CREATE_MINT_METADATA_DISC => MeteoraInstruction::CreateMintMetadata {
+                accounts: CreateMintMetadataAccounts::from_accounts(
+                    "create_mint_metadata",
+                    accounts,
+                )?,
+            },
+            CREATE_LOCK_ESCROW_DISC => MeteoraInstruction::CreateLockEscrow {
+                accounts: CreateLockEscrowAccounts::from_accounts("create_lock_escrow", accounts)?,
+            },
+            LOCK_DISC => {
+                let name = "lock";
+                MeteoraInstruction::Lock {
+                    accounts: LockAccounts::from_accounts(name, accounts)?,
+                    max_amount: args(name, data)?,
+                }
+            }
+            CLAIM_FEE_DISC => {
+                let name = "claim_fee";
+                MeteoraInstruction::ClaimFee {
+                    accounts: ClaimFeeAccounts::from_accounts(name, accounts)?,
+                    max_amount: args(name, data)?,
+                }
+            }
+            CREATE_CONFIG_DISC => {
+                let name = "create_config";
+                MeteoraInstruction::CreateConfig {
+                    accounts: CreateConfigAccounts::from_accounts(name, accounts)?,
+                    config_parameters: args(name, data)?,
+                }
+            }
+            CLOSE_CONFIG_DISC => MeteoraInstruction::CloseConfig {
+                accounts: CloseConfigAccounts::from_accounts("close_config", accounts)?,
+            },
+            UPDATE_ACTIVATION_POINT_DISC => {
+                let name = "update_activation_point";
+                MeteoraInstruction::UpdateActivationPoint {
+                    accounts: PoolAdminAccounts::from_accounts(name, accounts)?,
+                    new_activation_point: args(name, data)?,
+                }
+            }
+            WITHDRAW_PROTOCOL_FEES_DISC => MeteoraInstruction::WithdrawProtocolFees {
+                accounts: WithdrawProtocolFeesAccounts::from_accounts(
+                    "withdraw_protocol_fees",
+                    accounts,
+                )?,
+            },
+            SET_WHITELISTED_VAULT_DISC => {
+                let name = "set_whitelisted_vault";
+                MeteoraInstruction::SetWhitelistedVault {
+                    accounts: PoolAdminAccounts::from_accounts(name, accounts)?,
+                    whitelisted_vault: args(name, data)?,
+                }
+            }
+            PARTNER_CLAIM_FEE_DISC => {
+                let name = "partner_claim_fee";
+                MeteoraInstruction::PartnerClaimFee {
+                    accounts: PartnerClaimFeeAccounts::from_accounts(name, accounts)?,
+                    args: args(name, data)?,
+                }
+            }
+            unknown => return Err(DecodeError::UnknownDiscriminator(unknown)),
+        };
+
+        Ok(decoded)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MeteoraInstruction::InitializePermissionedPool { .. } => "initialize_permissioned_pool",
+            MeteoraInstruction::InitializePermissionlessPool { .. } => {
+                "initialize_permissionless_pool"
+            }
+            MeteoraInstruction::InitializePermissionlessPoolWithFeeTier { .. } => {
+                "initialize_permissionless_pool_with_fee_tier"
+            }
+            MeteoraInstruction::InitializePermissionlessConstantProductPoolWithConfig { .. } => {
+                "initialize_permissionless_constant_product_pool_with_config"
+            }
+            MeteoraInstruction::InitializePermissionlessConstantProductPoolWithConfig2 { .. } => {
+                "initialize_permissionless_constant_product_pool_with_config2"
+            }
+            MeteoraInstruction::InitializeCustomizablePermissionlessConstantProductPool { .. } => {
+                "initialize_customizable_permissionless_constant_product_pool"
+            }
+            MeteoraInstruction::EnableOrDisablePool { .. } => "enable_or_disable_pool",
+            MeteoraInstruction::Swap { .. } => "swap",
+            MeteoraInstruction::RemoveLiquiditySingleSide { .. } => "remove_liquidity_single_side",
+            MeteoraInstruction::AddImbalanceLiquidity { .. } => "add_imbalance_liquidity",
+            MeteoraInstruction::RemoveBalanceLiquidity { .. } => "remove_balance_liquidity",
+            MeteoraInstruction::AddBalanceLiquidity { .. } => "add_balance_liquidity",
+            MeteoraInstruction::SetPoolFees { .. } => "set_pool_fees",
+            MeteoraInstruction::OverrideCurveParam { .. } => "override_curve_param",
+            MeteoraInstruction::GetPoolInfo { .. } => "get_pool_info",
+            MeteoraInstruction::BootstrapLiquidity { .. } => "bootstrap_liquidity",
+            MeteoraInstruction::CreateMintMetadata { .. } => "create_mint_metadata",
+            MeteoraInstruction::CreateLockEscrow { .. } => "create_lock_escrow",
+            MeteoraInstruction::Lock { .. } => "lock",
+            MeteoraInstruction::ClaimFee { .. } => "claim_fee",
+            MeteoraInstruction::CreateConfig { .. } => "create_config",
+            MeteoraInstruction::CloseConfig { .. } => "close_config",
+            MeteoraInstruction::UpdateActivationPoint { .. } => "update_activation_point",
+            MeteoraInstruction::WithdrawProtocolFees { .. } => "withdraw_protocol_fees",
+            MeteoraInstruction::SetWhitelistedVault { .. } => "set_whitelisted_vault",
+            MeteoraInstruction::PartnerClaimFee { .. } => "partner_claim_fee",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::hashv;
+    use solana_sdk::instruction::AccountMeta;
+
+    // enough zeroed bytes for the args of every instruction, zero decodes to a valid value
+    const ARGS_LEN: usize = 256;
+
+    // (discriminator, name, accounts, has args)
+    const INSTRUCTIONS: [([u8; 8], &str, usize, bool); 26] = [
+        (
+            INITIALIZE_PERMISSIONED_POOL_DISC,
+            "initialize_permissioned_pool",
+            InitializePermissionedPoolAccounts::LEN,
+            true,
+        ),
+        (
+            INITIALIZE_PERMISSIONLESS_POOL_DISC,
+            "initialize_permissionless_pool",
+            InitializePermissionlessPoolAccounts::LEN,
+            true,
+        ),
+        (
+            INITIALIZE_PERMISSIONLESS_POOL_WITH_FEE_TIER_DISC,
+            "initialize_permissionless_pool_with_fee_tier",
+            InitializePermissionlessPoolAccounts::LEN,
+            true,
+        ),
+        (
+            INITIALIZE_PERMISSIONLESS_CONSTANT_PRODUCT_POOL_WITH_CONFIG_DISC,
+            "initialize_permissionless_constant_product_pool_with_config",
+            InitializePermissionlessPoolWithConfigAccounts::LEN,
+            true,
+        ),
+        (
+            INITIALIZE_PERMISSIONLESS_CONSTANT_PRODUCT_POOL_WITH_CONFIG_2_DISC,
+            "initialize_permissionless_constant_product_pool_with_config2",
+            InitializePermissionlessPoolWithConfigAccounts::LEN,
+            true,
+        ),
+        (
+            INITIALIZE_CUSTOMIZABLE_PERMISSIONLESS_CONSTANT_PRODUCT_POOL_DISC,
+            "initialize_customizable_permissionless_constant_product_pool",
+            InitializeCustomizablePermissionlessPoolAccounts::LEN,
+            true,
+        ),
+        (
+            ENABLE_OR_DISABLE_POOL_DISC,
+            "enable_or_disable_pool",
+            PoolAdminAccounts::LEN,
+            true,
+        ),
+        (SWAP_DISC, "swap", SwapAccounts::LEN, true),
+        (
+            REMOVE_LIQUIDITY_SINGLE_SIDE_DISC,
+            "remove_liquidity_single_side",
+            RemoveLiquiditySingleSideAccounts::LEN,
+            true,
+        ),
+        (
+            ADD_IMBALANCE_LIQUIDITY_DISC,
+            "add_imbalance_liquidity",
+            LiquidityAccounts::LEN,
+            true,
+        ),
+        (
+            REMOVE_BALANCE_LIQUIDITY_DISC,
+            "remove_balance_liquidity",
+            LiquidityAccounts::LEN,
+            true,
+        ),
+        (
+            ADD_BALANCE_LIQUIDITY_DISC,
+            "add_balance_liquidity",
+            LiquidityAccounts::LEN,
+            true,
+        ),
+        (
+            SET_POOL_FEES_DISC,
+            "set_pool_fees",
+            SetPoolFeesAccounts::LEN,
+            true,
+        ),
+        (
+            OVERRIDE_CURVE_PARAM_DISC,
+            "override_curve_param",
+            PoolAdminAccounts::LEN,
+            true,
+        ),
+        (
+            GET_POOL_INFO_DISC,
+            "get_pool_info",
+            GetPoolInfoAccounts::LEN,
+            false,
+        ),
+        (
+            BOOTSTRAP_LIQUIDITY_DISC,
+            "bootstrap_liquidity",
+            LiquidityAccounts::LEN,
+            true,
+        ),
+        (
+            CREATE_MINT_METADATA_DISC,
+            "create_mint_metadata",
+            CreateMintMetadataAccounts::LEN,
+            false,
+        ),
+        (
+            CREATE_LOCK_ESCROW_DISC,
+            "create_lock_escrow",
+            CreateLockEscrowAccounts::LEN,
+            false,
+        ),
+        (LOCK_DISC, "lock", LockAccounts::LEN, true),
+        (CLAIM_FEE_DISC, "claim_fee", ClaimFeeAccounts::LEN, true),
+        (
+            CREATE_CONFIG_DISC,
+            "create_config",
+            CreateConfigAccounts::LEN,
+            true,
+        ),
+        (
+            CLOSE_CONFIG_DISC,
+            "close_config",
+            CloseConfigAccounts::LEN,
+            false,
+        ),
+        (
+            UPDATE_ACTIVATION_POINT_DISC,
+            "update_activation_point",
+            PoolAdminAccounts::LEN,
+            true,
+        ),
+        (
+            WITHDRAW_PROTOCOL_FEES_DISC,
+            "withdraw_protocol_fees",
+            WithdrawProtocolFeesAccounts::LEN,
+            false,
+        ),
+        (
+            SET_WHITELISTED_VAULT_DISC,
+            "set_whitelisted_vault",
+            PoolAdminAccounts::LEN,
+            true,
+        ),
+        (
+            PARTNER_CLAIM_FEE_DISC,
+            "partner_claim_fee",
+            PartnerClaimFeeAccounts::LEN,
+            true,
+        ),
+    ];
+
+    fn instruction(discriminator: [u8; 8], args: &[u8], accounts: usize) -> Instruction {
+        Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: (0..accounts)
+                .map(|_| AccountMeta::new(Pubkey::new_unique(), false))
+                .collect(),
+            data: [discriminator.as_slice(), args].concat(),
+        }
+    }
+
+    #[test]
+    fn discriminators_match_names() {
+        for (discriminator, name, _, _) in INSTRUCTIONS {
+            let hash = hashv(&[b"global:", name.as_bytes()]).to_bytes();
+            assert_eq!(&hash[..8], &discriminator[..], "{}", name);
+        }
+    }
+
+    #[test]
+    fn decodes_every_instruction() {
+        for (discriminator, name, accounts, _) in INSTRUCTIONS {
+            let instruction = instruction(discriminator, &[0; ARGS_LEN], accounts);
+            let decoded = MeteoraInstruction::decode(&instruction).unwrap();
+            assert_eq!(decoded.name(), name);
+        }
+    }
+
+    #[test]
+    fn decodes_accounts_and_args() {
+        let args = borsh::to_vec(&SwapArgs {
+            in_amount: 1_000,
+            minimum_out_amount: 900,
+        })
+        .unwrap();
+        let instruction = instruction(SWAP_DISC, &args, SwapAccounts::LEN);
+
+        let MeteoraInstruction::Swap { accounts, args } =
+            MeteoraInstruction::decode(&instruction).unwrap()
+        else {
+            panic!("not a swap");
+        };
+        assert_eq!(accounts.pool, instruction.accounts[0].pubkey);
+        assert_eq!(accounts.user, instruction.accounts[12].pubkey);
+        assert_eq!(args.in_amount, 1_000);
+        assert_eq!(args.minimum_out_amount, 900);
+    }
+
+    #[test]
+    fn extra_accounts_and_trailing_data_are_ignored() {
+        let instruction = instruction(SWAP_DISC, &[0; ARGS_LEN], SwapAccounts::LEN + 3);
+        assert!(MeteoraInstruction::decode(&instruction).is_ok());
+    }
+
+    #[test]
+    fn unknown_discriminator() {
+        let instruction = instruction([0; 8], &[0; ARGS_LEN], SwapAccounts::LEN);
+        assert!(matches!(
+            MeteoraInstruction::decode(&instruction),
+            Err(DecodeError::UnknownDiscriminator(discriminator)) if discriminator == [0; 8]
+        ));
+    }
+
+    #[test]
+    fn missing_discriminator() {
+        let mut instruction = instruction(SWAP_DISC, &[], SwapAccounts::LEN);
+        instruction.data.truncate(5);
+        assert!(matches!(
+            MeteoraInstruction::decode(&instruction),
+            Err(DecodeError::MissingDiscriminator(5))
+        ));
+    }
+
+    #[test]
+    fn truncated_args() {
+        for (discriminator, name, accounts, has_args) in INSTRUCTIONS {
+            let decoded = MeteoraInstruction::decode(&instruction(discriminator, &[], accounts));
+            if has_args {
+                match decoded {
+                    Err(DecodeError::TruncatedData(truncated)) => assert_eq!(truncated, name),
+                    other => panic!("{}: {:?}", name, other),
+                }
+            } else {
+                assert!(decoded.is_ok(), "{}", name);
+            }
+        }
+
+        // half of the swap's amounts
+        let instruction = instruction(SWAP_DISC, &[0; 12], SwapAccounts::LEN);
+        assert!(matches!(
+            MeteoraInstruction::decode(&instruction),
+            Err(DecodeError::TruncatedData("swap"))
+        ));
+    }
+
+    #[test]
+    fn invalid_args() {
+        // a bool is 0 or 1
+        let instruction = instruction(ENABLE_OR_DISABLE_POOL_DISC, &[2], PoolAdminAccounts::LEN);
+        assert!(matches!(
+            MeteoraInstruction::decode(&instruction),
+            Err(DecodeError::InvalidData("enable_or_disable_pool", _))
+        ));
+    }
+
+    #[test]
+    fn not_enough_accounts() {
+        for (discriminator, name, accounts, _) in INSTRUCTIONS {
+            let instruction = instruction(discriminator, &[0; ARGS_LEN], accounts - 1);
+            match MeteoraInstruction::decode(&instruction) {
+                Err(DecodeError::NotEnoughAccounts {
+                    instruction: short,
+                    expected,
+                    actual,
+                }) => {
+                    assert_eq!(short, name);
+                    assert_eq!(expected, accounts);
+                    assert_eq!(actual, accounts - 1);
+                }
+                other => panic!("{}: {:?}", name, other),
+            }
+        }
+
+        let instruction = instruction(SWAP_DISC, &[0; ARGS_LEN], 0);
+        assert!(matches!(
+            MeteoraInstruction::decode(&instruction),
+            Err(DecodeError::NotEnoughAccounts { actual: 0, .. })
+        ));
+    }
+}
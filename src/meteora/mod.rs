@@ -1,41 +1,62 @@
-use crate::config::PingThingsArgs;
+use crate::config::{PingThingsArgs, PoolVariantsConfig};
 use crate::tx_senders::jito::JitoBundleStatusResponse;
 use crate::tx_senders::solana_rpc::TxMetrics;
 use crate::tx_senders::transaction::TransactionConfig;
 use crate::tx_senders::{create_tx_sender, TxResult, TxSender};
 
-use crate::bench::Bench;
-use crate::core::extract_instructions;
+use crate::bench::{Bench, SwapLanding};
+use crate::core::{extract_instructions, ExtractedInstruction};
+use crate::activation::{Activation, ActivationInfo, ActivationType, SlotClock};
+use crate::positions::{PositionManager, PositionStatus};
+use crate::exits::{token_balance, ExitMonitor};
+use crate::geyser::accounts::AccountUpdate;
+use crate::pricing::{fetch_tracker, PoolPriceTracker, PoolWatcher};
+use crate::dbc::{DbcInstruction, DbcMigration, DBC_PROGRAM_ID};
+use crate::filters::PoolFilter;
+use crate::safety::{MintInfo, SafetyChecker};
+use crate::damm_v2::{DammV2Instruction, DammV2PoolCreation, DammV2SwapData, DAMM_V2_PROGRAM_ID};
+use crate::dlmm::{
+    DlmmInstruction, DlmmPairCreation, DlmmSwapData, PendingPairs, DLMM_PROGRAM_ID,
+};
 use crate::tx_senders::constants::METEORA_PROGRAM_ADDR;
-use crate::WSOL_ACCOUNT_ID;
-use anyhow::anyhow;
+use crate::meteora::accounts::{Config, Pool, Vault};
+use crate::meteora::pda::validate_swap_data;
+use crate::meteora::instructions::{
+    CurveType, InitializeCustomizablePermissionlessPoolAccounts,
+    InitializePermissionlessPoolAccounts, InitializePermissionlessPoolWithConfigAccounts,
+    MeteoraInstruction,
+};
+use crate::quote::{quote, ui_amount_to_raw, PoolReserves, TradeFee};
+use crate::{METEORA_PROGRAM_ID, WSOL_ACCOUNT_ID};
+use anyhow::{anyhow, Context};
 use borsh::{BorshDeserialize, BorshSerialize};
 use futures::StreamExt;
 use log::debug;
 use reqwest::Client;
 use serde_json::json;
 use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::hash::Hash;
 use solana_sdk::instruction::AccountMeta;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::signature::Signature;
+use solana_sdk::signature::{Keypair, Signature, Signer};
 use solana_sdk::transaction::VersionedTransaction;
 use solana_transaction_status::TransactionStatusMeta;
-use spl_associated_token_account::solana_program::sysvar::instructions;
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
 use tokio::time::timeout;
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
-pub const INITIALIZE_PERMISSIONLESS_CONSTANT_PRODUCT_POOL_WITH_CONFIG_2_DISC: [u8; 8] =
-    [48, 149, 220, 130, 61, 11, 9, 178];
-pub const IX_DISCRIMINATOR_SIZE: usize = 8;
+pub mod accounts;
+pub mod instructions;
+pub mod pda;
 
 #[derive(Debug, BorshDeserialize, BorshSerialize, Clone)]
 pub struct AddLiquidityIxData {
@@ -96,25 +117,509 @@ impl SwapData {
             protocol_token_b_fee,
         }
     }
+
+    // Rebuilds everything a swap needs from the pool and vault accounts, for pools
+    // we didn't see being created. The accounts are checked like a creation's.
+    pub async fn from_pool_address(
+        rpc_client: &RpcClient,
+        address: Pubkey,
+    ) -> anyhow::Result<Self> {
+        let pool_account = rpc_client
+            .get_account(&address)
+            .await
+            .with_context(|| format!("cannot fetch pool {}", address))?;
+        if pool_account.owner != METEORA_PROGRAM_ID {
+            return Err(anyhow!(
+                "pool {} is owned by {}, not the dynamic AMM program",
+                address,
+                pool_account.owner
+            ));
+        }
+        let pool = Pool::decode(&pool_account.data)
+            .with_context(|| format!("cannot decode pool {}", address))?;
+        if !pool.enabled {
+            return Err(anyhow!("pool {} is disabled", address));
+        }
+
+        let vault_accounts = rpc_client
+            .get_multiple_accounts(&[pool.a_vault, pool.b_vault])
+            .await
+            .with_context(|| format!("cannot fetch vaults of pool {}", address))?;
+        let vault_addresses = [pool.a_vault, pool.b_vault];
+        let mut vaults = Vec::with_capacity(2);
+        for (vault_address, account) in vault_addresses.into_iter().zip(vault_accounts) {
+            let account = account.ok_or_else(|| anyhow!("vault {} not found", vault_address))?;
+            let vault = Vault::decode(&account.data)
+                .with_context(|| format!("cannot decode vault {}", vault_address))?;
+            // every vault is owned by the dynamic vault program
+            vaults.push((vault, account.owner));
+        }
+        let (b_vault, _) = vaults.pop().unwrap();
+        let (a_vault, vault_program) = vaults.pop().unwrap();
+
+        let swap_data = SwapData::new(
+            address,
+            pool.token_a_mint,
+            pool.token_b_mint,
+            pool.a_vault,
+            pool.b_vault,
+            a_vault.token_vault,
+            b_vault.token_vault,
+            a_vault.lp_mint,
+            b_vault.lp_mint,
+            pool.a_vault_lp,
+            pool.b_vault_lp,
+            vault_program,
+            pool.protocol_token_a_fee,
+            pool.protocol_token_b_fee,
+        );
+        validate_swap_data(&swap_data)
+            .with_context(|| format!("rejected accounts of pool {}", address))?;
+        Ok(swap_data)
+    }
+}
+
+// What to swap against: a pool of one of the supported Meteora programs.
+#[derive(Debug, Clone)]
+pub enum SwapTarget {
+    DynamicAmm(SwapData),
+    Dlmm(DlmmSwapData),
+    DammV2(DammV2SwapData),
+}
+
+impl SwapTarget {
+    pub fn pool(&self) -> Pubkey {
+        match self {
+            SwapTarget::DynamicAmm(swap_data) => swap_data.pool,
+            SwapTarget::Dlmm(swap_data) => swap_data.lb_pair,
+            SwapTarget::DammV2(swap_data) => swap_data.pool,
+        }
+    }
+
+    pub fn mints(&self) -> (Pubkey, Pubkey) {
+        match self {
+            SwapTarget::DynamicAmm(swap_data) => (swap_data.token_a_mint, swap_data.token_b_mint),
+            SwapTarget::Dlmm(swap_data) => (swap_data.token_x_mint, swap_data.token_y_mint),
+            SwapTarget::DammV2(swap_data) => (swap_data.token_a_mint, swap_data.token_b_mint),
+        }
+    }
+
+    pub fn has_mint(&self, mint: &Pubkey) -> bool {
+        let (mint_a, mint_b) = self.mints();
+        mint_a == *mint || mint_b == *mint
+    }
+
+    // token programs owning `mints()`, in the same order
+    pub fn token_programs(&self) -> (Pubkey, Pubkey) {
+        match self {
+            // the dynamic AMM vaults only work with spl-token mints
+            SwapTarget::DynamicAmm(_) => (spl_token::ID, spl_token::ID),
+            SwapTarget::Dlmm(swap_data) => (swap_data.token_x_program, swap_data.token_y_program),
+            SwapTarget::DammV2(swap_data) => (swap_data.token_a_program, swap_data.token_b_program),
+        }
+    }
+
+    pub fn token_program(&self, mint: &Pubkey) -> Pubkey {
+        let (mint_a, _) = self.mints();
+        let (program_a, program_b) = self.token_programs();
+        if *mint == mint_a {
+            program_a
+        } else {
+            program_b
+        }
+    }
+
+    // the side of the pool that isn't WSOL, i.e. the token we buy
+    pub fn token_mint(&self) -> Pubkey {
+        let (mint_a, mint_b) = self.mints();
+        if mint_a == WSOL_ACCOUNT_ID {
+            mint_b
+        } else {
+            mint_a
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapSide {
+    // WSOL -> token
+    Buy,
+    // token -> WSOL
+    Sell,
+}
+
+// One swap to send: the pool, the direction, what we pay in and the least we accept out.
+#[derive(Debug, Clone)]
+pub struct SwapOrder {
+    pub target: SwapTarget,
+    pub side: SwapSide,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+}
+
+// A freshly created pool and everything the filters and scheduler need to know about it.
+#[derive(Debug, Clone)]
+pub struct PoolCandidate {
+    pub target: SwapTarget,
+    pub activation: ActivationInfo,
+    pub creator: Option<Pubkey>,
+    // pool config account (fee tier), when the creation instruction names one
+    pub config: Option<Pubkey>,
+    // initial deposit in the order of `SwapTarget::mints`, when the instruction carries it
+    pub token_amounts: Option<(u64, u64)>,
+    // what the quote runs on, None when the pool can't be quoted
+    pub reserves: Option<PoolReserves>,
+    pub trade_fee: Option<TradeFee>,
+}
+
+impl PoolCandidate {
+    pub fn new(target: SwapTarget) -> Self {
+        PoolCandidate {
+            target,
+            activation: ActivationInfo::default(),
+            creator: None,
+            config: None,
+            token_amounts: None,
+            reserves: None,
+            trade_fee: None,
+        }
+    }
+
+    // initial WSOL deposit in lamports
+    pub fn sol_amount(&self) -> Option<u64> {
+        let (amount_a, amount_b) = self.token_amounts?;
+        let (mint_a, _) = self.target.mints();
+        Some(if mint_a == WSOL_ACCOUNT_ID {
+            amount_a
+        } else {
+            amount_b
+        })
+    }
+}
+
+// The three pool-creation account layouts differ in order and extras (config, fee owner),
+// but all of them carry everything a swap needs.
+macro_rules! swap_data_from_accounts {
+    ($accounts:ty) => {
+        impl From<$accounts> for SwapData {
+            fn from(accounts: $accounts) -> Self {
+                SwapData::new(
+                    accounts.pool,
+                    accounts.token_a_mint,
+                    accounts.token_b_mint,
+                    accounts.a_vault,
+                    accounts.b_vault,
+                    accounts.a_token_vault,
+                    accounts.b_token_vault,
+                    accounts.a_vault_lp_mint,
+                    accounts.b_vault_lp_mint,
+                    accounts.a_vault_lp,
+                    accounts.b_vault_lp,
+                    accounts.vault_program,
+                    accounts.protocol_token_a_fee,
+                    accounts.protocol_token_b_fee,
+                )
+            }
+        }
+    };
+}
+
+swap_data_from_accounts!(InitializePermissionlessPoolAccounts);
+swap_data_from_accounts!(InitializePermissionlessPoolWithConfigAccounts);
+swap_data_from_accounts!(InitializeCustomizablePermissionlessPoolAccounts);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolCreationKind {
+    Permissionless,
+    PermissionlessWithFeeTier,
+    WithConfig,
+    WithConfig2,
+    Customizable,
+}
+
+impl PoolCreationKind {
+    pub fn is_enabled(&self, variants: &PoolVariantsConfig) -> bool {
+        match self {
+            PoolCreationKind::Permissionless => variants.permissionless,
+            PoolCreationKind::PermissionlessWithFeeTier => variants.permissionless_with_fee_tier,
+            PoolCreationKind::WithConfig => variants.with_config,
+            PoolCreationKind::WithConfig2 => variants.with_config2,
+            PoolCreationKind::Customizable => variants.customizable,
+        }
+    }
+}
+
+// customizable pools express their trade fee over 100_000
+pub const CUSTOMIZABLE_FEE_DENOMINATOR: u64 = 100_000;
+
+// A decoded pool-creation instruction, whatever variant created the pool.
+#[derive(Debug, Clone)]
+pub struct PoolCreation {
+    pub kind: PoolCreationKind,
+    pub swap_data: SwapData,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+    pub activation_point: Option<u64>,
+    pub activation_type: Option<ActivationType>,
+    // None when the fee lives in the config account or is the program default
+    pub trade_fee: Option<TradeFee>,
+    pub stable: bool,
+    // pool config account, only set for the with_config variants
+    pub config: Option<Pubkey>,
+    pub creator: Pubkey,
+}
+
+impl PoolCreation {
+    pub fn from_instruction(instruction: MeteoraInstruction) -> Option<PoolCreation> {
+        let creation = match instruction {
+            MeteoraInstruction::InitializePermissionlessPool { accounts, args } => PoolCreation {
+                kind: PoolCreationKind::Permissionless,
+                swap_data: accounts.into(),
+                token_a_amount: args.token_a_amount,
+                token_b_amount: args.token_b_amount,
+                activation_point: None,
+                activation_type: None,
+                trade_fee: None,
+                stable: matches!(args.curve_type, CurveType::Stable { .. }),
+                config: None,
+                creator: accounts.payer,
+            },
+            MeteoraInstruction::InitializePermissionlessPoolWithFeeTier { accounts, args } => {
+                PoolCreation {
+                    kind: PoolCreationKind::PermissionlessWithFeeTier,
+                    swap_data: accounts.into(),
+                    token_a_amount: args.token_a_amount,
+                    token_b_amount: args.token_b_amount,
+                    activation_point: None,
+                    activation_type: None,
+                    trade_fee: Some(TradeFee::from_bps(args.trade_fee_bps)),
+                    stable: matches!(args.curve_type, CurveType::Stable { .. }),
+                    config: None,
+                    creator: accounts.payer,
+                }
+            }
+            MeteoraInstruction::InitializePermissionlessConstantProductPoolWithConfig {
+                accounts,
+                args,
+            } => PoolCreation {
+                kind: PoolCreationKind::WithConfig,
+                swap_data: accounts.into(),
+                token_a_amount: args.token_a_amount,
+                token_b_amount: args.token_b_amount,
+                activation_point: None,
+                activation_type: None,
+                trade_fee: None,
+                stable: false,
+                config: Some(accounts.config),
+                creator: accounts.payer,
+            },
+            MeteoraInstruction::InitializePermissionlessConstantProductPoolWithConfig2 {
+                accounts,
+                args,
+            } => PoolCreation {
+                kind: PoolCreationKind::WithConfig2,
+                swap_data: accounts.into(),
+                token_a_amount: args.tokenAAmount,
+                token_b_amount: args.tokenBAmount,
+                activation_point: args.activationPoint,
+                activation_type: None,
+                trade_fee: None,
+                stable: false,
+                config: Some(accounts.config),
+                creator: accounts.payer,
+            },
+            MeteoraInstruction::InitializeCustomizablePermissionlessConstantProductPool {
+                accounts,
+                args,
+            } => PoolCreation {
+                kind: PoolCreationKind::Customizable,
+                swap_data: accounts.into(),
+                token_a_amount: args.token_a_amount,
+                token_b_amount: args.token_b_amount,
+                activation_point: args.params.activation_point,
+                activation_type: ActivationType::from_u8(args.params.activation_type),
+                trade_fee: Some(TradeFee {
+                    numerator: args.params.trade_fee_numerator as u64,
+                    denominator: CUSTOMIZABLE_FEE_DENOMINATOR,
+                }),
+                stable: false,
+                config: None,
+                creator: accounts.payer,
+            },
+            _ => return None,
+        };
+
+        Some(creation)
+    }
+
+    pub fn activation(&self) -> ActivationInfo {
+        ActivationInfo {
+            point: self.activation_point,
+            activation_type: self.activation_type,
+            config: self.config,
+        }
+    }
+
+    // stable swap curves can't be quoted with the constant-product formula
+    pub fn reserves(&self) -> Option<PoolReserves> {
+        (!self.stable).then_some(PoolReserves::ConstantProduct {
+            amount_a: self.token_a_amount,
+            amount_b: self.token_b_amount,
+        })
+    }
 }
 
 pub struct MeteoraController {
     config: PingThingsArgs,
     bench: Bench,
-
-    is_buy: bool,
+    rpc_client: Arc<RpcClient>,
+    clock: SlotClock,
+    // DLMM pairs created without liquidity yet
+    dlmm_pairs: PendingPairs,
+    positions: Arc<Mutex<PositionManager>>,
+    buyer: Buyer,
+    bought: mpsc::UnboundedReceiver<PoolPriceTracker>,
+    // live snapshots of the pools we hold
+    pools: PoolWatcher,
+    exits: ExitMonitor,
 }
 
 impl MeteoraController {
     pub fn new(config: PingThingsArgs, bench: Bench) -> Self {
+        let rpc_client = Arc::new(RpcClient::new(config.http_rpc.clone()));
+        let owner = Keypair::from_base58_string(config.private_key.as_str()).pubkey();
+        let slot_duration = Duration::from_millis(config.activation.slot_duration_ms);
+        let positions = Arc::new(Mutex::new(PositionManager::new(
+            config.positions.max_open_positions,
+        )));
+        let (bought_sender, bought) = mpsc::unbounded_channel();
+        let buyer = Buyer {
+            config: Arc::new(config.clone()),
+            bench: bench.clone(),
+            rpc_client: rpc_client.clone(),
+            owner,
+            pool_configs: Arc::new(RwLock::new(HashMap::new())),
+            positions: positions.clone(),
+            filter: Arc::new(PoolFilter::new(&config.filters, slot_duration)),
+            safety: Arc::new(SafetyChecker::new(config.safety.clone(), rpc_client.clone())),
+            bought: bought_sender,
+        };
+        let pools = PoolWatcher::new(rpc_client.clone());
+        let exits = ExitMonitor::new(
+            &config,
+            bench.clone(),
+            rpc_client.clone(),
+            positions.clone(),
+            pools.snapshots(),
+        );
         MeteoraController {
             config,
             bench: bench,
-            is_buy: false,
+            rpc_client,
+            clock: SlotClock::new(slot_duration),
+            dlmm_pairs: PendingPairs::default(),
+            positions,
+            buyer,
+            bought,
+            pools,
+            exits,
+        }
+    }
+
+    // Picks up the positions listed in config, e.g. bought before a restart. They are
+    // tracked like positions opened by this session.
+    pub async fn load_existing_positions(&mut self) {
+        for address in self.config.positions.existing_pools.clone() {
+            let Ok(pool) = Pubkey::from_str(&address) else {
+                warn!("skipping existing position {}: not a valid address", address);
+                continue;
+            };
+            let swap_data = match SwapData::from_pool_address(&self.rpc_client, pool).await {
+                Ok(swap_data) => swap_data,
+                Err(err) => {
+                    warn!("skipping existing position {}: {:?}", pool, err);
+                    continue;
+                }
+            };
+
+            let target = SwapTarget::DynamicAmm(swap_data);
+            if let Err(reason) = self
+                .positions
+                .lock()
+                .unwrap()
+                .open(target.clone(), PositionStatus::Open)
+            {
+                warn!("skipping existing position {}: {}", pool, reason);
+                continue;
+            }
+            self.pools.watch(&target).await;
+            self.exits.track_existing(&target);
+            info!("loaded existing position on {}", pool);
+        }
+    }
+
+    // reserve accounts of the pools we hold, to stream from geyser
+    pub fn watched_accounts(&self) -> tokio::sync::watch::Receiver<Vec<Pubkey>> {
+        self.pools.watched_accounts()
+    }
+
+    pub fn report_pools(&self) {
+        for pool in self.pools.pools() {
+            match self.pools.snapshot(&pool) {
+                Some(snapshot) => info!(
+                    "pool {}: {:e} lamports per token unit, reserves {:?} at slot {}",
+                    pool, snapshot.price, snapshot.reserves, snapshot.slot
+                ),
+                None => info!("pool {}: no price yet", pool),
+            }
         }
     }
 
-    pub async fn transaction_handler(
+    pub fn exit_check_interval(&self) -> Duration {
+        self.exits.check_interval()
+    }
+
+    // The next pool the buyer bought, None once it is gone.
+    pub async fn next_bought(&mut self) -> Option<PoolPriceTracker> {
+        self.bought.recv().await
+    }
+
+    pub fn track(&mut self, tracker: PoolPriceTracker) {
+        let target = tracker.target().clone();
+        self.pools.insert(tracker);
+        self.exits.track(&target);
+    }
+
+    pub fn account_handler(&mut self, update: AccountUpdate) {
+        for pool in self.pools.update(update) {
+            self.exits.evaluate(&pool);
+        }
+    }
+
+    pub fn check_exits(&mut self) {
+        self.exits.check_all();
+
+        // closed positions don't need prices anymore
+        let closed: Vec<Pubkey> = {
+            let positions = self.positions.lock().unwrap();
+            self.pools
+                .pools()
+                .into_iter()
+                .filter(|pool| {
+                    positions
+                        .get(pool)
+                        .map_or(true, |position| position.status == PositionStatus::Closed)
+                })
+                .collect()
+        };
+        for pool in closed {
+            self.pools.unwatch(&pool);
+        }
+    }
+
+    // Decodes `transaction` for new pools and hands the candidates to the buyer's own task:
+    // the RPC round trips of a buy would hold up every transaction behind this one.
+    pub fn transaction_handler(
         &mut self,
         signature: Signature,
         transaction: VersionedTransaction,
@@ -123,80 +628,590 @@ impl MeteoraController {
         slot: u64,
     ) -> anyhow::Result<()> {
         // info!("INSIDE meteora tx handler");
-        let instructions: Vec<(solana_sdk::instruction::Instruction)> =
+        self.clock.observe(slot);
+        self.bench.observe_signature(&signature, slot);
+
+        let instructions: Vec<ExtractedInstruction> =
             extract_instructions(meta, transaction.clone())?;
 
-        if !self.is_buy {
-            for (instruction) in instructions {
-                if instruction.program_id == Pubkey::from_str(METEORA_PROGRAM_ADDR)? {
-                    let ix_discriminator: [u8; 8] =
-                        instruction.data[0..IX_DISCRIMINATOR_SIZE].try_into()?;
-
-                    // info!("INSIDE meteora tx handler {:?}", ix_discriminator);
-
-                    let mut ix_data = &instruction.data[IX_DISCRIMINATOR_SIZE..];
-
-                    let create_ix_data: AddLiquidityIxData =
-                        BorshDeserialize::deserialize(&mut ix_data)?;
-
-                    if ix_discriminator
-                        == INITIALIZE_PERMISSIONLESS_CONSTANT_PRODUCT_POOL_WITH_CONFIG_2_DISC
-                    {
-                        info!("create ix: {:?}", create_ix_data);
-
-                        let pool = instruction.accounts[0].pubkey;
-
-                        let token_a_mint = instruction.accounts[3].pubkey;
-                        let token_b_mint = instruction.accounts[4].pubkey;
-
-                        if vec![token_a_mint, token_b_mint].contains(&WSOL_ACCOUNT_ID) {
-                            info!("create ix: {:?}", create_ix_data);
-
-                            let a_vault = instruction.accounts[5].pubkey;
-                            let b_vault = instruction.accounts[6].pubkey;
-
-                            let a_token_vault = instruction.accounts[7].pubkey;
-                            let b_token_vault = instruction.accounts[8].pubkey;
-
-                            let a_vault_lp_mint = instruction.accounts[9].pubkey;
-                            let b_vault_lp_mint = instruction.accounts[10].pubkey;
-
-                            let a_vault_lp = instruction.accounts[11].pubkey;
-                            let b_vault_lp = instruction.accounts[12].pubkey;
-
-                            let protocol_token_a_fee = instruction.accounts[16].pubkey;
-                            let protocol_token_b_fee = instruction.accounts[17].pubkey;
-                            let vault_programm = instruction.accounts[22].pubkey;
-
-                            let swap_data: SwapData = SwapData::new(
-                                pool,
-                                token_a_mint,
-                                token_b_mint,
-                                a_vault,
-                                b_vault,
-                                a_token_vault,
-                                b_token_vault,
-                                a_vault_lp_mint,
-                                b_vault_lp_mint,
-                                a_vault_lp,
-                                b_vault_lp,
-                                vault_programm,
-                                protocol_token_a_fee,
-                                protocol_token_b_fee,
-                            );
-
-                            let recent_blockhash: Hash = *transaction.message.recent_blockhash();
-                            self.is_buy = true;
-                            self.bench
-                                .clone()
-                                .send_swap_tx(recent_blockhash, swap_data)
-                                .await;
-                        }
-                    }
+        let mut candidates = Vec::new();
+        for extracted in &instructions {
+            let candidate = if extracted.instruction.program_id == METEORA_PROGRAM_ID {
+                self.dynamic_amm_target(&signature, extracted)
+            } else if extracted.instruction.program_id == DLMM_PROGRAM_ID {
+                self.dlmm_target(&signature, extracted)
+            } else if extracted.instruction.program_id == DAMM_V2_PROGRAM_ID {
+                self.damm_v2_target(&signature, extracted)
+            } else if extracted.instruction.program_id == DBC_PROGRAM_ID {
+                self.dbc_migration_target(&signature, extracted, &instructions)
+            } else {
+                None
+            };
+
+            let Some(candidate) = candidate else {
+                continue;
+            };
+            let swap_target = &candidate.target;
+
+            if extracted.is_cpi() {
+                info!(
+                    "pool created via CPI in {} (outer ix {}, stack height {})",
+                    signature, extracted.outer_index, extracted.stack_height
+                );
+            }
+
+            if swap_target.has_mint(&WSOL_ACCOUNT_ID) {
+                if let Err(reason) = self.positions.lock().unwrap().check(swap_target) {
+                    info!("skipping pool {}: {}", swap_target.pool(), reason);
+                    continue;
                 }
+                candidates.push(candidate);
             }
         }
 
+        if !candidates.is_empty() {
+            let recent_blockhash: Hash = *transaction.message.recent_blockhash();
+            let buyer = self.buyer.clone();
+            tokio::spawn(buyer.buy(candidates, recent_blockhash, self.clock, slot));
+        }
         Ok(())
     }
+
+    fn dynamic_amm_target(
+        &self,
+        signature: &Signature,
+        extracted: &ExtractedInstruction,
+    ) -> Option<PoolCandidate> {
+        if !self.config.programs.dynamic_amm {
+            return None;
+        }
+
+        let decoded = match MeteoraInstruction::decode(&extracted.instruction) {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                debug!("skipping meteora instruction in {}: {}", signature, err);
+                return None;
+            }
+        };
+
+        let name = decoded.name();
+        let creation = PoolCreation::from_instruction(decoded)?;
+
+        if !creation.kind.is_enabled(&self.config.pool_variants) {
+            info!("skipping {} pool {}: variant disabled in config", name, creation.swap_data.pool);
+            return None;
+        }
+
+        if let Err(mismatch) = validate_swap_data(&creation.swap_data) {
+            let pool = creation.swap_data.pool;
+            warn!("rejected {} pool {} in {}: {}", name, pool, signature, mismatch);
+            return None;
+        }
+
+        info!("create ix {}: {:?}", name, creation);
+        Some(PoolCandidate {
+            target: SwapTarget::DynamicAmm(creation.swap_data),
+            activation: creation.activation(),
+            creator: Some(creation.creator),
+            config: creation.config,
+            token_amounts: Some((creation.token_a_amount, creation.token_b_amount)),
+            reserves: creation.reserves(),
+            trade_fee: creation.trade_fee,
+        })
+    }
+
+    // A new pair is only bought once liquidity was added, see `PendingPairs`.
+    fn dlmm_target(
+        &mut self,
+        signature: &Signature,
+        extracted: &ExtractedInstruction,
+    ) -> Option<PoolCandidate> {
+        if !self.config.programs.dlmm {
+            return None;
+        }
+
+        let decoded = match DlmmInstruction::decode(&extracted.instruction) {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                debug!("skipping dlmm instruction in {}: {}", signature, err);
+                return None;
+            }
+        };
+
+        let name = decoded.name();
+        let Some(creation) = self.dlmm_pairs.update(&decoded) else {
+            let creation = DlmmPairCreation::from_instruction(decoded, &WSOL_ACCOUNT_ID)?;
+            info!("create ix {}: {:?}", name, creation);
+            info!("dlmm pair {} waits for liquidity", creation.swap_data.lb_pair);
+            self.dlmm_pairs.insert(creation);
+            return None;
+        };
+        info!(
+            "{} on dlmm pair {} in {}, buying through bin arrays {:?}",
+            name, creation.swap_data.lb_pair, signature, creation.swap_data.bin_arrays
+        );
+
+        // the liquidity add doesn't tell the amounts either
+        Some(PoolCandidate {
+            activation: creation.activation(),
+            creator: Some(creation.creator),
+            ..PoolCandidate::new(SwapTarget::Dlmm(creation.swap_data))
+        })
+    }
+
+    fn damm_v2_target(
+        &self,
+        signature: &Signature,
+        extracted: &ExtractedInstruction,
+    ) -> Option<PoolCandidate> {
+        if !self.config.programs.damm_v2 {
+            return None;
+        }
+
+        let decoded = match DammV2Instruction::decode(&extracted.instruction) {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                debug!("skipping damm v2 instruction in {}: {}", signature, err);
+                return None;
+            }
+        };
+
+        let name = decoded.name();
+        let creation = DammV2PoolCreation::from_instruction(decoded);
+        info!("create ix {}: {:?}", name, creation);
+
+        // the deposit is expressed as liquidity, not token amounts
+        Some(PoolCandidate {
+            activation: creation.activation(),
+            creator: Some(creation.creator),
+            config: creation.config,
+            reserves: Some(creation.reserves()),
+            trade_fee: creation.trade_fee,
+            ..PoolCandidate::new(SwapTarget::DammV2(creation.swap_data))
+        })
+    }
+
+    // A DBC migration creates the graduated pool through a CPI issued under the same
+    // top-level instruction, that CPI carries the reserves that were moved.
+    fn dbc_migration_target(
+        &self,
+        signature: &Signature,
+        extracted: &ExtractedInstruction,
+        instructions: &[ExtractedInstruction],
+    ) -> Option<PoolCandidate> {
+        if !self.config.programs.dbc {
+            return None;
+        }
+
+        let decoded = match DbcInstruction::decode(&extracted.instruction) {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                debug!("skipping dbc instruction in {}: {}", signature, err);
+                return None;
+            }
+        };
+
+        let name = decoded.name();
+        let mut migration = DbcMigration::from_instruction(decoded);
+        if let SwapTarget::DynamicAmm(swap_data) = &migration.swap_target {
+            if let Err(mismatch) = validate_swap_data(swap_data) {
+                warn!("rejected {} pool {} in {}: {}", name, swap_data.pool, signature, mismatch);
+                return None;
+            }
+        }
+
+        let found_creation = instructions
+            .iter()
+            .filter(|inner| inner.is_cpi() && inner.outer_index == extracted.outer_index)
+            .any(|inner| migration.apply_creation(&inner.instruction));
+        if !found_creation {
+            warn!(
+                "{} in {}: pool creation CPI not found, reserves unknown",
+                name, signature
+            );
+        }
+
+        info!("dbc {}: {:?}", name, migration);
+        // graduated pools are tradable as soon as the migration lands
+        let token_amounts = match migration.reserves {
+            Some(PoolReserves::ConstantProduct { amount_a, amount_b }) => {
+                Some((amount_a, amount_b))
+            }
+            _ => None,
+        };
+        Some(PoolCandidate {
+            token_amounts,
+            reserves: migration.reserves,
+            trade_fee: migration.trade_fee,
+            ..PoolCandidate::new(migration.swap_target)
+        })
+    }
+}
+
+// Everything between a pool candidate and its buy that needs the RPC: the activation,
+// the mint checks, the quote and the send.
+#[derive(Clone)]
+struct Buyer {
+    config: Arc<PingThingsArgs>,
+    bench: Bench,
+    rpc_client: Arc<RpcClient>,
+    // the wallet buying, fills are read from its token accounts
+    owner: Pubkey,
+    // dynamic AMM pool configs by address, they never change once created
+    pool_configs: Arc<RwLock<HashMap<Pubkey, Config>>>,
+    positions: Arc<Mutex<PositionManager>>,
+    filter: Arc<PoolFilter>,
+    safety: Arc<SafetyChecker>,
+    // pools just bought, for the controller to watch
+    bought: mpsc::UnboundedSender<PoolPriceTracker>,
+}
+
+impl Buyer {
+    // Buys the first of `candidates`, all from one transaction, that passes every check.
+    async fn buy(
+        self,
+        candidates: Vec<PoolCandidate>,
+        recent_blockhash: Hash,
+        clock: SlotClock,
+        slot: u64,
+    ) {
+        for candidate in candidates {
+            if self.try_buy(candidate, recent_blockhash, clock, slot).await {
+                break;
+            }
+        }
+    }
+
+    async fn try_buy(
+        &self,
+        candidate: PoolCandidate,
+        recent_blockhash: Hash,
+        clock: SlotClock,
+        slot: u64,
+    ) -> bool {
+        let swap_target = candidate.target.clone();
+        let activation = self.resolve_activation(candidate.activation, slot).await;
+        let wait = activation
+            .map(|activation| clock.time_until(&activation, self.lead_time()))
+            .unwrap_or_default();
+
+        if wait > Duration::from_secs(self.config.activation.max_wait_secs) {
+            info!(
+                "skipping pool {}: activates at {:?}, {:?} away",
+                swap_target.pool(),
+                activation,
+                wait
+            );
+            return false;
+        }
+
+        let pending = (!wait.is_zero()).then_some(wait);
+        if let Err(reason) = self.filter.check(&candidate, pending) {
+            info!("rejected pool {}: {}", swap_target.pool(), reason);
+            return false;
+        }
+
+        let mint = swap_target.token_mint();
+        let mint_info = match self.safety.fetch_mint(&mint).await {
+            Ok(mint_info) => mint_info,
+            Err(reason) => {
+                info!("rejected pool {}: mint {}: {}", swap_target.pool(), mint, reason);
+                return false;
+            }
+        };
+        if let Err(reason) = self.safety.check(&mint_info) {
+            info!("rejected pool {}: mint {}: {}", swap_target.pool(), mint, reason);
+            return false;
+        }
+        // e.g. a token-2022 mint in a dynamic AMM pool, the swap could never succeed
+        if mint_info.token_program != swap_target.token_program(&mint) {
+            info!(
+                "rejected pool {}: mint {} is owned by {}, the pool expects {}",
+                swap_target.pool(),
+                mint,
+                mint_info.token_program,
+                swap_target.token_program(&mint)
+            );
+            return false;
+        }
+
+        let Some(swap_order) = self.swap_order(&candidate, &mint_info).await else {
+            return false;
+        };
+
+        let status = if wait.is_zero() {
+            PositionStatus::Open
+        } else {
+            PositionStatus::Scheduled
+        };
+        let opened = {
+            let mut positions = self.positions.lock().unwrap();
+            positions
+                .open(swap_target.clone(), status)
+                .map(|_| positions.open_positions())
+        };
+        match opened {
+            Ok(open) => info!("opened position on {} ({} open)", swap_target.pool(), open),
+            Err(reason) => {
+                info!("skipping pool {}: {}", swap_target.pool(), reason);
+                return false;
+            }
+        }
+
+        if wait.is_zero() {
+            let amount_in = swap_order.amount_in;
+            let landing = self
+                .bench
+                .clone()
+                .send_swap_tx(recent_blockhash, swap_order, Some(slot))
+                .await;
+            tokio::spawn(follow_buy(
+                self.positions.clone(),
+                self.rpc_client.clone(),
+                self.owner,
+                swap_target.clone(),
+                amount_in,
+                landing,
+            ));
+        } else {
+            self.schedule_swap_tx(swap_order, activation, wait, slot);
+        }
+
+        let tracker = fetch_tracker(&self.rpc_client, &swap_target).await;
+        // the controller is gone on shutdown only
+        let _ = self.bought.send(tracker);
+        true
+    }
+
+    fn lead_time(&self) -> Duration {
+        Duration::from_millis(self.config.activation.lead_time_ms)
+    }
+
+    // Fills the gaps in what the creation instruction told us: the activation type and,
+    // for configs with an activation duration, the activation point itself.
+    async fn resolve_activation(&self, info: ActivationInfo, slot: u64) -> Option<Activation> {
+        let pool_config = match info.config {
+            Some(address) => self.pool_config(address).await,
+            None => None,
+        };
+
+        let activation_type = info.activation_type.or_else(|| {
+            pool_config
+                .as_ref()
+                .and_then(|config| ActivationType::from_u8(config.activation_type))
+        });
+
+        let point = match (info.point, &pool_config) {
+            (Some(point), _) => point,
+            (None, Some(config)) if config.activation_duration > 0 => {
+                let created_at = match activation_type {
+                    Some(ActivationType::Timestamp) => SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs(),
+                    _ => slot,
+                };
+                created_at + config.activation_duration
+            }
+            _ => return None,
+        };
+
+        Some(Activation {
+            point,
+            activation_type: activation_type.unwrap_or_else(|| ActivationType::guess(point)),
+        })
+    }
+
+    async fn pool_config(&self, address: Pubkey) -> Option<Config> {
+        if let Some(config) = self.pool_configs.read().unwrap().get(&address) {
+            return Some(config.clone());
+        }
+
+        let data = match self.rpc_client.get_account_data(&address).await {
+            Ok(data) => data,
+            Err(err) => {
+                warn!("cannot fetch pool config {}: {:?}", address, err);
+                return None;
+            }
+        };
+
+        match Config::decode(&data) {
+            Ok(config) => {
+                self.pool_configs.write().unwrap().insert(address, config.clone());
+                Some(config)
+            }
+            Err(err) => {
+                debug!("pool config {} not decoded: {}", address, err);
+                None
+            }
+        }
+    }
+
+    // Sizes the buy: quotes the pool's initial reserves when the creation carried them and
+    // the pool's fee is known, otherwise falls back to the fixed `min_amount_out` from config.
+    async fn swap_order(
+        &self,
+        candidate: &PoolCandidate,
+        mint_info: &MintInfo,
+    ) -> Option<SwapOrder> {
+        let target = candidate.target.clone();
+        let amount_in = (self.config.buy_amount * LAMPORTS_PER_SOL as f64) as u64;
+
+        let Some(reserves) = candidate.reserves else {
+            return Some(self.fixed_swap_order(target, amount_in, mint_info, "reserves"));
+        };
+        let trade_fee = match candidate.trade_fee {
+            Some(trade_fee) => trade_fee,
+            None => match self.config_trade_fee(candidate).await {
+                Some(trade_fee) => trade_fee,
+                // a guessed fee could make min out unreachable, or far too loose
+                None => return Some(self.fixed_swap_order(target, amount_in, mint_info, "fee")),
+            },
+        };
+        let (mint_a, _) = target.mints();
+        let a_to_b = mint_a == WSOL_ACCOUNT_ID;
+
+        let Some(quote) = quote(
+            &reserves,
+            &trade_fee,
+            a_to_b,
+            amount_in,
+            mint_info.transfer_fee.as_ref(),
+            self.config.quote.slippage_bps,
+        ) else {
+            info!("rejected pool {}: cannot quote reserves {:?}", target.pool(), reserves);
+            return None;
+        };
+        info!(
+            "quote for {}: {:?} (trade fee {} bps)",
+            target.pool(),
+            quote,
+            trade_fee.bps()
+        );
+
+        if let Some(max_price_impact_bps) = self.config.quote.max_price_impact_bps {
+            if quote.price_impact_bps > max_price_impact_bps {
+                info!(
+                    "rejected pool {}: price impact {} bps above {} bps",
+                    target.pool(),
+                    quote.price_impact_bps,
+                    max_price_impact_bps
+                );
+                return None;
+            }
+        }
+
+        Some(SwapOrder {
+            target,
+            side: SwapSide::Buy,
+            amount_in,
+            min_amount_out: quote.min_amount_out,
+        })
+    }
+
+    fn fixed_swap_order(
+        &self,
+        target: SwapTarget,
+        amount_in: u64,
+        mint_info: &MintInfo,
+        unknown: &str,
+    ) -> SwapOrder {
+        let min_amount_out = ui_amount_to_raw(self.config.min_amount_out, mint_info.decimals);
+        info!(
+            "pool {} has no known {}, using fixed min out {}",
+            target.pool(),
+            unknown,
+            min_amount_out
+        );
+        SwapOrder {
+            target,
+            side: SwapSide::Buy,
+            amount_in,
+            min_amount_out,
+        }
+    }
+
+    // None when the pool's fee can't be told, e.g. for DAMM v2 configs which aren't decoded.
+    async fn config_trade_fee(&self, candidate: &PoolCandidate) -> Option<TradeFee> {
+        let address = match (&candidate.target, candidate.config) {
+            (SwapTarget::DynamicAmm(_), Some(address)) => address,
+            _ => return None,
+        };
+        let config = self.pool_config(address).await?;
+        Some(TradeFee {
+            numerator: config.pool_fees.trade_fee_numerator,
+            denominator: config.pool_fees.trade_fee_denominator,
+        })
+    }
+
+    // Holds the swap until just before activation, then sends it with a fresh blockhash:
+    // the creation transaction's one may have expired by then.
+    fn schedule_swap_tx(
+        &self,
+        swap_order: SwapOrder,
+        activation: Option<Activation>,
+        wait: Duration,
+        created_slot: u64,
+    ) {
+        info!(
+            "pool {} activates at {:?}, sending in {:?}",
+            swap_order.target.pool(),
+            activation,
+            wait
+        );
+
+        let bench = self.bench.clone();
+        let rpc_client = self.rpc_client.clone();
+        let positions = self.positions.clone();
+        let owner = self.owner;
+        tokio::spawn(async move {
+            tokio::time::sleep(wait).await;
+
+            let pool = swap_order.target.pool();
+            let recent_blockhash = match rpc_client.get_latest_blockhash().await {
+                Ok(blockhash) => blockhash,
+                Err(err) => {
+                    error!("cannot fetch blockhash for scheduled swap on {}: {:?}", pool, err);
+                    positions.lock().unwrap().close(&pool);
+                    return;
+                }
+            };
+            positions.lock().unwrap().set_status(&pool, PositionStatus::Open);
+            let target = swap_order.target.clone();
+            let amount_in = swap_order.amount_in;
+            let landing = bench
+                .send_swap_tx(recent_blockhash, swap_order, Some(created_slot))
+                .await;
+            follow_buy(positions, rpc_client, owner, target, amount_in, landing).await;
+        });
+    }
+}
+
+// A buy that never landed leaves nothing to sell, its slot goes back to the position limit.
+// One that did sets the entry price from what it actually paid per token.
+async fn follow_buy(
+    positions: Arc<Mutex<PositionManager>>,
+    rpc_client: Arc<RpcClient>,
+    owner: Pubkey,
+    target: SwapTarget,
+    amount_in: u64,
+    landing: SwapLanding,
+) {
+    let pool = target.pool();
+    if landing.await.ok().flatten().is_none() {
+        warn!("buy on {} did not land, closing its position", pool);
+        positions.lock().unwrap().close(&pool);
+        return;
+    }
+
+    // a mint is bought once per session, so the whole balance came from this buy
+    match token_balance(&rpc_client, owner, &target).await {
+        Ok(received) if received > 0 => {
+            let entry_price = amount_in as f64 / received as f64;
+            info!("entry price of {}: {:e} lamports per token unit", pool, entry_price);
+            positions.lock().unwrap().set_entry_price(&pool, entry_price);
+        }
+        Ok(_) => warn!("buy on {} landed but no tokens arrived yet", pool),
+        Err(err) => warn!("cannot read the fill of {}: {:?}", pool, err),
+    }
 }
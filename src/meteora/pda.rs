@@ -0,0 +1,187 @@
+use crate::meteora::SwapData;
+use crate::METEORA_PROGRAM_ID;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+
+pub const VAULT_PROGRAM_ID: Pubkey = pubkey!("24Uqj9JCLxUeoC3hGfh5W3s9FM9uCHDS2SG3LYwBpyTi");
+// base key every Meteora-managed vault is derived with
+pub const VAULT_BASE_KEY: Pubkey = pubkey!("HWzXGcGHy4tcpYfaRDCyLNzXqBTv3E6BttpCH2vJxArv");
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PdaMismatch {
+    #[error("vault program is {0}, expected {}", VAULT_PROGRAM_ID)]
+    VaultProgram(Pubkey),
+    #[error("{account} is {actual}, expected {expected}")]
+    Account {
+        account: &'static str,
+        expected: Pubkey,
+        actual: Pubkey,
+    },
+}
+
+pub fn vault(token_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"vault", token_mint.as_ref(), VAULT_BASE_KEY.as_ref()],
+        &VAULT_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn token_vault(vault: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"token_vault", vault.as_ref()], &VAULT_PROGRAM_ID).0
+}
+
+pub fn vault_lp_mint(vault: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"lp_mint", vault.as_ref()], &VAULT_PROGRAM_ID).0
+}
+
+// the pool's LP token account of a vault
+pub fn vault_lp(vault: &Pubkey, pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[vault.as_ref(), pool.as_ref()], &METEORA_PROGRAM_ID).0
+}
+
+pub fn protocol_fee(token_mint: &Pubkey, pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"fee", token_mint.as_ref(), pool.as_ref()],
+        &METEORA_PROGRAM_ID,
+    )
+    .0
+}
+
+fn expect(account: &'static str, expected: Pubkey, actual: Pubkey) -> Result<(), PdaMismatch> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(PdaMismatch::Account {
+            account,
+            expected,
+            actual,
+        })
+    }
+}
+
+// Re-derives every vault and fee account from the mints and the pool address instead of
+// trusting the instruction's account list, so a crafted transaction can't point our swap
+// at accounts someone else controls.
+pub fn validate_swap_data(swap_data: &SwapData) -> Result<(), PdaMismatch> {
+    if swap_data.vault_programm != VAULT_PROGRAM_ID {
+        return Err(PdaMismatch::VaultProgram(swap_data.vault_programm));
+    }
+
+    let pool = &swap_data.pool;
+    let a_vault = vault(&swap_data.token_a_mint);
+    let b_vault = vault(&swap_data.token_b_mint);
+
+    expect("a_vault", a_vault, swap_data.a_vault)?;
+    expect("b_vault", b_vault, swap_data.b_vault)?;
+    expect("a_token_vault", token_vault(&a_vault), swap_data.a_token_vault)?;
+    expect("b_token_vault", token_vault(&b_vault), swap_data.b_token_vault)?;
+    expect("a_vault_lp_mint", vault_lp_mint(&a_vault), swap_data.a_vault_lp_mint)?;
+    expect("b_vault_lp_mint", vault_lp_mint(&b_vault), swap_data.b_vault_lp_mint)?;
+    expect("a_vault_lp", vault_lp(&a_vault, pool), swap_data.a_vault_lp)?;
+    expect("b_vault_lp", vault_lp(&b_vault, pool), swap_data.b_vault_lp)?;
+    expect(
+        "protocol_token_a_fee",
+        protocol_fee(&swap_data.token_a_mint, pool),
+        swap_data.protocol_token_a_fee,
+    )?;
+    expect(
+        "protocol_token_b_fee",
+        protocol_fee(&swap_data.token_b_mint, pool),
+        swap_data.protocol_token_b_fee,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WSOL_ACCOUNT_ID;
+    use std::mem::swap;
+
+    // a token/WSOL pool, the WSOL side uses mainnet's SOL vault
+    fn known_pool() -> SwapData {
+        SwapData::new(
+            pubkey!("US517G5965aydkZ46HS38QLi7UQiSojurfbQfKCELFx"),
+            pubkey!("4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi"),
+            WSOL_ACCOUNT_ID,
+            pubkey!("5a7pWhZG5s34T9dxuHPJHcnQ1rFDT6JDYkWsFL4E7URF"),
+            pubkey!("FERjPVNEa7Udq8CEv68h6tPL46Tq7ieE49HrE2wea3XT"),
+            pubkey!("6msk6DNnh4kc8NaPGPP2XC3a5U46aJReZJBrxHNZzq1F"),
+            pubkey!("HZeLxbZ9uHtSpwZC3LBr4Nubd14iHwz7bRSghRZf5VCG"),
+            pubkey!("3ZcbCHnwbkGBNwKQ2hoiw3cs45NhJjPUQfgJFeryDft8"),
+            pubkey!("9ERA9Zg3Z1eDw8M7kvHdUz7UKXcTBz1k7pPXLhM3ay7Z"),
+            pubkey!("Eo8aVA1WMZmuTzCs9STE7J1XYCNwmVg34WNmL1GuwaS8"),
+            pubkey!("EdB6oTVcqgUTU3trbd3LpYTPbUeQmnS2UBZHWo2JX3bs"),
+            VAULT_PROGRAM_ID,
+            pubkey!("9T3R4j1JZhuQaqBd7n1EtsM745RxmSry9ZMhNsbfTehN"),
+            pubkey!("94WkPyXChx5e4Qp3gDn6kNHcxWsBrLhq1HbPbwDfjRX8"),
+        )
+    }
+
+    #[test]
+    fn known_pool_is_valid() {
+        assert_eq!(validate_swap_data(&known_pool()), Ok(()));
+    }
+
+    #[test]
+    fn rejects_swapped_accounts() {
+        let swaps: [(&str, fn(&mut SwapData)); 5] = [
+            ("a_vault", |data| swap(&mut data.a_vault, &mut data.b_vault)),
+            ("a_token_vault", |data| {
+                swap(&mut data.a_token_vault, &mut data.b_token_vault)
+            }),
+            ("a_vault_lp_mint", |data| {
+                swap(&mut data.a_vault_lp_mint, &mut data.b_vault_lp_mint)
+            }),
+            ("a_vault_lp", |data| {
+                swap(&mut data.a_vault_lp, &mut data.b_vault_lp)
+            }),
+            ("protocol_token_a_fee", |data| {
+                swap(
+                    &mut data.protocol_token_a_fee,
+                    &mut data.protocol_token_b_fee,
+                )
+            }),
+        ];
+
+        for (account, swap_accounts) in swaps {
+            let mut swap_data = known_pool();
+            swap_accounts(&mut swap_data);
+            assert!(
+                matches!(
+                    validate_swap_data(&swap_data),
+                    Err(PdaMismatch::Account { account: mismatch, .. }) if mismatch == account
+                ),
+                "{}",
+                account
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_accounts_of_another_pool() {
+        let mut swap_data = known_pool();
+        swap_data.b_vault_lp = vault_lp(&swap_data.b_vault, &Pubkey::new_unique());
+        assert_eq!(
+            validate_swap_data(&swap_data),
+            Err(PdaMismatch::Account {
+                account: "b_vault_lp",
+                expected: known_pool().b_vault_lp,
+                actual: swap_data.b_vault_lp,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_other_vault_program() {
+        let mut swap_data = known_pool();
+        swap_data.vault_programm = Pubkey::new_unique();
+        assert_eq!(
+            validate_swap_data(&swap_data),
+            Err(PdaMismatch::VaultProgram(swap_data.vault_programm))
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::meteora::SwapTarget;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionStatus {
+    // buy is waiting for the pool's activation point
+    Scheduled,
+    // buy was sent
+    Open,
+    // an exit rule fired, the sell is being sent
+    Closing,
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub target: SwapTarget,
+    pub mint: Pubkey,
+    pub status: PositionStatus,
+    pub opened_at: Instant,
+    // lamports per token unit the buy executed at, known once it landed
+    pub entry_price: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    PoolAlreadyTracked,
+    MintAlreadyBought,
+    MaxOpenPositions(usize),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::PoolAlreadyTracked => write!(f, "pool already has a position"),
+            SkipReason::MintAlreadyBought => write!(f, "mint already bought this session"),
+            SkipReason::MaxOpenPositions(max) => write!(f, "{} positions already open", max),
+        }
+    }
+}
+
+// Tracks every pool we bought into during the session, keyed by pool address.
+pub struct PositionManager {
+    max_open_positions: usize,
+    positions: HashMap<Pubkey, Position>,
+    // a mint is only bought once, even if it shows up again in another pool
+    bought_mints: HashSet<Pubkey>,
+}
+
+impl PositionManager {
+    pub fn new(max_open_positions: usize) -> Self {
+        PositionManager {
+            max_open_positions,
+            positions: HashMap::new(),
+            bought_mints: HashSet::new(),
+        }
+    }
+
+    pub fn open_positions(&self) -> usize {
+        self.positions
+            .values()
+            .filter(|position| position.status != PositionStatus::Closed)
+            .count()
+    }
+
+    pub fn check(&self, target: &SwapTarget) -> Result<(), SkipReason> {
+        if self.positions.contains_key(&target.pool()) {
+            return Err(SkipReason::PoolAlreadyTracked);
+        }
+        if self.bought_mints.contains(&target.token_mint()) {
+            return Err(SkipReason::MintAlreadyBought);
+        }
+        if self.open_positions() >= self.max_open_positions {
+            return Err(SkipReason::MaxOpenPositions(self.max_open_positions));
+        }
+        Ok(())
+    }
+
+    // Reserves a slot for `target`, the caller sends the buy right after.
+    pub fn open(&mut self, target: SwapTarget, status: PositionStatus) -> Result<(), SkipReason> {
+        self.check(&target)?;
+
+        let mint = target.token_mint();
+        self.bought_mints.insert(mint);
+        self.positions.insert(
+            target.pool(),
+            Position {
+                target,
+                mint,
+                status,
+                opened_at: Instant::now(),
+                entry_price: None,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn set_status(&mut self, pool: &Pubkey, status: PositionStatus) {
+        if let Some(position) = self.positions.get_mut(pool) {
+            position.status = status;
+        }
+    }
+
+    pub fn set_entry_price(&mut self, pool: &Pubkey, entry_price: f64) {
+        if let Some(position) = self.positions.get_mut(pool) {
+            position.entry_price = Some(entry_price);
+        }
+    }
+
+    // Frees the slot; the mint stays blocked so it isn't bought a second time.
+    pub fn close(&mut self, pool: &Pubkey) {
+        self.set_status(pool, PositionStatus::Closed);
+    }
+
+    pub fn get(&self, pool: &Pubkey) -> Option<&Position> {
+        self.positions.get(pool)
+    }
+}
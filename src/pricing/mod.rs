@@ -0,0 +1,329 @@
+use crate::dlmm::LbPairState;
+use crate::geyser::accounts::AccountUpdate;
+use crate::meteora::accounts::{Pool, Vault};
+use crate::meteora::instructions::CurveType;
+use crate::meteora::SwapTarget;
+use crate::quote::{PoolReserves, TradeFee};
+use crate::WSOL_ACCOUNT_ID;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::StateWithExtensions;
+use spl_token_2022::state::{Account as TokenAccount, Mint};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+// The pool's price at the latest account data we have.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceSnapshot {
+    // lamports per raw unit of the token
+    pub price: f64,
+    // in `SwapTarget::mints` order, for pools a sell can be quoted on
+    pub reserves: Option<PoolReserves>,
+    // the pool's current fee, when its account carries it
+    pub trade_fee: Option<TradeFee>,
+    // DLMM only, the bin the next swap starts from
+    pub active_id: Option<i32>,
+    pub slot: u64,
+}
+
+// Prices one pool from the accounts holding its reserves, fed by geyser account updates.
+#[derive(Debug, Clone)]
+pub struct PoolPriceTracker {
+    target: SwapTarget,
+    // account -> (slot, data) of the latest update
+    accounts: HashMap<Pubkey, (u64, Vec<u8>)>,
+}
+
+impl PoolPriceTracker {
+    pub fn new(target: SwapTarget) -> Self {
+        PoolPriceTracker {
+            target,
+            accounts: HashMap::new(),
+        }
+    }
+
+    pub fn target(&self) -> &SwapTarget {
+        &self.target
+    }
+
+    pub fn watched_accounts(&self) -> Vec<Pubkey> {
+        reserve_accounts(&self.target)
+    }
+
+    // Updates from several endpoints can arrive out of order, older data is dropped.
+    pub fn update(&mut self, account: Pubkey, slot: u64, data: Vec<u8>) {
+        match self.accounts.get(&account) {
+            Some((last_slot, _)) if *last_slot > slot => {}
+            _ => {
+                self.accounts.insert(account, (slot, data));
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> Option<PriceSnapshot> {
+        let slot = self.accounts.values().map(|(slot, _)| *slot).max()?;
+
+        match &self.target {
+            SwapTarget::DynamicAmm(swap_data) => {
+                let pool = Pool::decode(self.data(&swap_data.pool)?).ok()?;
+                // stable curves are priced by the invariant, not by the reserve ratio
+                if !matches!(pool.curve_type, CurveType::ConstantProduct) {
+                    return None;
+                }
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                // the pool holds vault LP tokens, its reserves are its share of each vault
+                let amount_a = self.vault_share(
+                    &swap_data.a_vault,
+                    &swap_data.a_vault_lp_mint,
+                    &swap_data.a_vault_lp,
+                    now,
+                )?;
+                let amount_b = self.vault_share(
+                    &swap_data.b_vault,
+                    &swap_data.b_vault_lp_mint,
+                    &swap_data.b_vault_lp,
+                    now,
+                )?;
+                let trade_fee = TradeFee {
+                    numerator: pool.fees.trade_fee_numerator,
+                    denominator: pool.fees.trade_fee_denominator,
+                };
+                self.constant_product(amount_a, amount_b, Some(trade_fee), slot)
+            }
+            // with the full price range DAMM v2 launches use, the vault balances behave
+            // like constant-product reserves
+            SwapTarget::DammV2(swap_data) => {
+                let amount_a = self.token_amount(&swap_data.token_a_vault)?;
+                let amount_b = self.token_amount(&swap_data.token_b_vault)?;
+                self.constant_product(amount_a, amount_b, None, slot)
+            }
+            SwapTarget::Dlmm(swap_data) => {
+                let state = LbPairState::decode(self.data(&swap_data.lb_pair)?)?;
+                let price_x_in_y = state.price();
+                let price = if swap_data.token_x_mint == WSOL_ACCOUNT_ID {
+                    1.0 / price_x_in_y
+                } else {
+                    price_x_in_y
+                };
+                Some(PriceSnapshot {
+                    price,
+                    reserves: None,
+                    trade_fee: None,
+                    active_id: Some(state.active_id),
+                    slot,
+                })
+            }
+        }
+    }
+
+    fn constant_product(
+        &self,
+        amount_a: u64,
+        amount_b: u64,
+        trade_fee: Option<TradeFee>,
+        slot: u64,
+    ) -> Option<PriceSnapshot> {
+        let (mint_a, _) = self.target.mints();
+        let (sol, token) = if mint_a == WSOL_ACCOUNT_ID {
+            (amount_a, amount_b)
+        } else {
+            (amount_b, amount_a)
+        };
+        if token == 0 {
+            return None;
+        }
+
+        Some(PriceSnapshot {
+            price: sol as f64 / token as f64,
+            reserves: Some(PoolReserves::ConstantProduct { amount_a, amount_b }),
+            trade_fee,
+            active_id: None,
+            slot,
+        })
+    }
+
+    fn vault_share(
+        &self,
+        vault: &Pubkey,
+        lp_mint: &Pubkey,
+        pool_lp: &Pubkey,
+        now: u64,
+    ) -> Option<u64> {
+        let vault = Vault::decode(self.data(vault)?).ok()?;
+        let lp_supply = StateWithExtensions::<Mint>::unpack(self.data(lp_mint)?)
+            .ok()?
+            .base
+            .supply;
+        let share = self.token_amount(pool_lp)?;
+        vault.amount_by_share(now, share, lp_supply)
+    }
+
+    fn token_amount(&self, account: &Pubkey) -> Option<u64> {
+        let account = StateWithExtensions::<TokenAccount>::unpack(self.data(account)?).ok()?;
+        Some(account.base.amount)
+    }
+
+    fn data(&self, account: &Pubkey) -> Option<&[u8]> {
+        self.accounts.get(account).map(|(_, data)| data.as_slice())
+    }
+}
+
+// Accounts whose data the pool's price is computed from.
+pub fn reserve_accounts(target: &SwapTarget) -> Vec<Pubkey> {
+    match target {
+        SwapTarget::DynamicAmm(swap_data) => vec![
+            swap_data.pool,
+            swap_data.a_vault,
+            swap_data.b_vault,
+            swap_data.a_vault_lp_mint,
+            swap_data.b_vault_lp_mint,
+            swap_data.a_vault_lp,
+            swap_data.b_vault_lp,
+        ],
+        SwapTarget::Dlmm(swap_data) => vec![swap_data.lb_pair],
+        SwapTarget::DammV2(swap_data) => vec![swap_data.token_a_vault, swap_data.token_b_vault],
+    }
+}
+
+// A tracker primed with the pool's current reserves: geyser only sends changes, a quiet
+// pool would never get a first snapshot.
+pub async fn fetch_tracker(rpc_client: &RpcClient, target: &SwapTarget) -> PoolPriceTracker {
+    let mut tracker = PoolPriceTracker::new(target.clone());
+    let accounts = tracker.watched_accounts();
+
+    match rpc_client
+        .get_multiple_accounts_with_commitment(&accounts, CommitmentConfig::processed())
+        .await
+    {
+        Ok(response) => {
+            let slot = response.context.slot;
+            for (account, data) in accounts.iter().zip(response.value) {
+                if let Some(data) = data {
+                    tracker.update(*account, slot, data.data);
+                }
+            }
+        }
+        Err(err) => warn!("cannot fetch reserves of {}: {:?}", target.pool(), err),
+    }
+    tracker
+}
+
+// Latest snapshot of every watched pool, shared with whoever needs prices.
+pub type PoolSnapshots = Arc<RwLock<HashMap<Pubkey, PriceSnapshot>>>;
+
+// Keeps a price and reserve snapshot of the pools we hold, from geyser account updates
+// of their reserve accounts. The geyser account subscription follows `watched_accounts`.
+pub struct PoolWatcher {
+    rpc_client: Arc<RpcClient>,
+    trackers: HashMap<Pubkey, PoolPriceTracker>,
+    // reserve account -> pools priced from it, dynamic AMM vaults are shared between pools
+    account_pools: HashMap<Pubkey, HashSet<Pubkey>>,
+    watched_accounts: watch::Sender<Vec<Pubkey>>,
+    snapshots: PoolSnapshots,
+}
+
+impl PoolWatcher {
+    pub fn new(rpc_client: Arc<RpcClient>) -> Self {
+        let (watched_accounts, _) = watch::channel(Vec::new());
+        PoolWatcher {
+            rpc_client,
+            trackers: HashMap::new(),
+            account_pools: HashMap::new(),
+            watched_accounts,
+            snapshots: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn watched_accounts(&self) -> watch::Receiver<Vec<Pubkey>> {
+        self.watched_accounts.subscribe()
+    }
+
+    pub fn snapshots(&self) -> PoolSnapshots {
+        self.snapshots.clone()
+    }
+
+    pub fn pools(&self) -> Vec<Pubkey> {
+        self.trackers.keys().cloned().collect()
+    }
+
+    pub async fn watch(&mut self, target: &SwapTarget) {
+        if self.trackers.contains_key(&target.pool()) {
+            return;
+        }
+        let tracker = fetch_tracker(&self.rpc_client, target).await;
+        self.insert(tracker);
+    }
+
+    // Starts following a pool whose current reserves were fetched elsewhere.
+    pub fn insert(&mut self, tracker: PoolPriceTracker) {
+        let pool = tracker.target().pool();
+        if self.trackers.contains_key(&pool) {
+            return;
+        }
+
+        for account in tracker.watched_accounts() {
+            self.account_pools.entry(account).or_default().insert(pool);
+        }
+        self.store_snapshot(pool, tracker.snapshot());
+        self.trackers.insert(pool, tracker);
+        self.publish_watched_accounts();
+        info!("watching reserves of {}", pool);
+    }
+
+    pub fn unwatch(&mut self, pool: &Pubkey) {
+        let Some(tracker) = self.trackers.remove(pool) else {
+            return;
+        };
+        for account in tracker.watched_accounts() {
+            if let Some(pools) = self.account_pools.get_mut(&account) {
+                pools.remove(pool);
+                if pools.is_empty() {
+                    self.account_pools.remove(&account);
+                }
+            }
+        }
+        self.snapshots.write().unwrap().remove(pool);
+        self.publish_watched_accounts();
+    }
+
+    // Applies an account update, returns the pools whose snapshot was refreshed.
+    pub fn update(&mut self, update: AccountUpdate) -> Vec<Pubkey> {
+        let Some(pools) = self.account_pools.get(&update.account) else {
+            return Vec::new();
+        };
+
+        let pools: Vec<Pubkey> = pools.iter().cloned().collect();
+        for pool in &pools {
+            if let Some(tracker) = self.trackers.get_mut(pool) {
+                tracker.update(update.account, update.slot, update.data.clone());
+                let snapshot = tracker.snapshot();
+                self.store_snapshot(*pool, snapshot);
+            }
+        }
+        pools
+    }
+
+    pub fn snapshot(&self, pool: &Pubkey) -> Option<PriceSnapshot> {
+        self.snapshots.read().unwrap().get(pool).copied()
+    }
+
+    fn store_snapshot(&self, pool: Pubkey, snapshot: Option<PriceSnapshot>) {
+        let mut snapshots = self.snapshots.write().unwrap();
+        match snapshot {
+            Some(snapshot) => snapshots.insert(pool, snapshot),
+            None => snapshots.remove(&pool),
+        };
+    }
+
+    fn publish_watched_accounts(&self) {
+        self.watched_accounts
+            .send_replace(self.account_pools.keys().cloned().collect());
+    }
+}
@@ -0,0 +1,323 @@
+use crate::token::{amount_received, TransferFeeSchedule};
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+// DAMM v2 liquidity and sqrt prices are Q64.64 fixed point
+const Q64: f64 = 18_446_744_073_709_551_616.0;
+
+// Fee taken from the input amount. The protocol fee is a share of this trade fee,
+// not charged on top of it, so it doesn't change what the swapper receives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TradeFee {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl TradeFee {
+    pub fn from_bps(bps: u64) -> Self {
+        TradeFee {
+            numerator: bps,
+            denominator: BPS_DENOMINATOR,
+        }
+    }
+
+    pub fn bps(&self) -> u64 {
+        if self.denominator == 0 {
+            return 0;
+        }
+        self.numerator * BPS_DENOMINATOR / self.denominator
+    }
+
+    // input left after the fee, the fee is rounded up
+    pub fn amount_after_fee(&self, amount: u64) -> u64 {
+        if self.denominator == 0 {
+            return amount;
+        }
+        let fee = (amount as u128 * self.numerator as u128).div_ceil(self.denominator as u128);
+        amount.saturating_sub(fee as u64)
+    }
+}
+
+// Pool state right after creation, as described by the creation instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolReserves {
+    // x * y = k over token amounts (dynamic AMM constant-product pools)
+    ConstantProduct { amount_a: u64, amount_b: u64 },
+    // liquidity at a sqrt price of a in b, both Q64.64 (DAMM v2)
+    Concentrated { liquidity: u128, sqrt_price: u128 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quote {
+    pub amount_in: u64,
+    // what arrives in the wallet, after the pool fee and any transfer fee
+    pub expected_out: u64,
+    pub min_amount_out: u64,
+    // how much worse than the pre-trade spot price, fees excluded
+    pub price_impact_bps: u64,
+}
+
+// Quotes swapping `amount_in` of token a for token b (`a_to_b`) or the other way round.
+pub fn quote(
+    reserves: &PoolReserves,
+    trade_fee: &TradeFee,
+    a_to_b: bool,
+    amount_in: u64,
+    transfer_fee: Option<&TransferFeeSchedule>,
+    slippage_bps: u64,
+) -> Option<Quote> {
+    let amount_in_after_fee = trade_fee.amount_after_fee(amount_in);
+
+    let (out, spot_out) = match *reserves {
+        PoolReserves::ConstantProduct { amount_a, amount_b } => {
+            let (reserve_in, reserve_out) = if a_to_b {
+                (amount_a, amount_b)
+            } else {
+                (amount_b, amount_a)
+            };
+            if reserve_in == 0 || reserve_out == 0 {
+                return None;
+            }
+            let out = constant_product_out(reserve_in, reserve_out, amount_in_after_fee);
+            let spot_out = amount_in_after_fee as f64 * reserve_out as f64 / reserve_in as f64;
+            (out, spot_out)
+        }
+        PoolReserves::Concentrated {
+            liquidity,
+            sqrt_price,
+        } => concentrated_out(liquidity, sqrt_price, a_to_b, amount_in_after_fee)?,
+    };
+
+    let expected_out = amount_received(transfer_fee, out);
+    let price_impact_bps = if spot_out > 0.0 {
+        ((1.0 - out as f64 / spot_out).max(0.0) * BPS_DENOMINATOR as f64) as u64
+    } else {
+        BPS_DENOMINATOR
+    };
+
+    Some(Quote {
+        amount_in,
+        expected_out,
+        min_amount_out: apply_slippage(expected_out, slippage_bps),
+        price_impact_bps,
+    })
+}
+
+pub fn constant_product_out(reserve_in: u64, reserve_out: u64, amount_in: u64) -> u64 {
+    let numerator = amount_in as u128 * reserve_out as u128;
+    let denominator = reserve_in as u128 + amount_in as u128;
+    (numerator / denominator) as u64
+}
+
+// Returns (out, spot out). Assumes the swap stays inside the pool's price range, which
+// holds for the first buy into a freshly created pool.
+fn concentrated_out(
+    liquidity: u128,
+    sqrt_price: u128,
+    a_to_b: bool,
+    amount_in: u64,
+) -> Option<(u64, f64)> {
+    let liquidity = liquidity as f64 / Q64;
+    let sqrt_price = sqrt_price as f64 / Q64;
+    if liquidity <= 0.0 || sqrt_price <= 0.0 {
+        return None;
+    }
+    let amount_in = amount_in as f64;
+
+    let (out, spot_out) = if a_to_b {
+        // price of a in b drops: sqrt_p' = L * sqrt_p / (L + in * sqrt_p)
+        let next = liquidity * sqrt_price / (liquidity + amount_in * sqrt_price);
+        (liquidity * (sqrt_price - next), amount_in * sqrt_price * sqrt_price)
+    } else {
+        // sqrt_p' = sqrt_p + in / L
+        let next = sqrt_price + amount_in / liquidity;
+        (
+            liquidity * (next - sqrt_price) / (sqrt_price * next),
+            amount_in / (sqrt_price * sqrt_price),
+        )
+    };
+
+    Some((out.max(0.0) as u64, spot_out))
+}
+
+pub fn apply_slippage(amount: u64, slippage_bps: u64) -> u64 {
+    let keep = BPS_DENOMINATOR.saturating_sub(slippage_bps);
+    (amount as u128 * keep as u128 / BPS_DENOMINATOR as u128) as u64
+}
+
+// Fixed minimum out in UI units, scaled by the mint's decimals.
+pub fn ui_amount_to_raw(amount: f64, decimals: u8) -> u64 {
+    (amount * 10f64.powi(decimals as i32)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONSTANT_PRODUCT: PoolReserves = PoolReserves::ConstantProduct {
+        amount_a: 1_000_000,
+        amount_b: 2_000_000,
+    };
+
+    // L = 1e6 at a price of 4 b per a
+    fn concentrated() -> PoolReserves {
+        PoolReserves::Concentrated {
+            liquidity: 1_000_000 * Q64 as u128,
+            sqrt_price: 2 * Q64 as u128,
+        }
+    }
+
+    #[test]
+    fn constant_product_a_to_b() {
+        let quote = quote(
+            &CONSTANT_PRODUCT,
+            &TradeFee::from_bps(25),
+            true,
+            10_000,
+            None,
+            500,
+        )
+        .unwrap();
+        // 25 lamports of fee, 9_975 * 2_000_000 / 1_009_975
+        assert_eq!(quote.amount_in, 10_000);
+        assert_eq!(quote.expected_out, 19_752);
+        assert_eq!(quote.min_amount_out, 18_764);
+        assert_eq!(quote.price_impact_bps, 99);
+    }
+
+    #[test]
+    fn constant_product_b_to_a() {
+        let quote = quote(
+            &CONSTANT_PRODUCT,
+            &TradeFee::from_bps(25),
+            false,
+            10_000,
+            None,
+            500,
+        )
+        .unwrap();
+        // 9_975 * 1_000_000 / 2_009_975
+        assert_eq!(quote.expected_out, 4_962);
+        assert_eq!(quote.min_amount_out, 4_713);
+        assert_eq!(quote.price_impact_bps, 51);
+    }
+
+    #[test]
+    fn constant_product_transfer_fee() {
+        let transfer_fee = TransferFeeSchedule {
+            basis_points: 100,
+            maximum_fee: u64::MAX,
+        };
+        let quote = quote(
+            &CONSTANT_PRODUCT,
+            &TradeFee::from_bps(25),
+            true,
+            10_000,
+            Some(&transfer_fee),
+            500,
+        )
+        .unwrap();
+        // 1% of the 19_752 leaving the pool is withheld, rounded up
+        assert_eq!(quote.expected_out, 19_554);
+        assert_eq!(quote.min_amount_out, 18_576);
+    }
+
+    #[test]
+    fn transfer_fee_is_capped() {
+        let transfer_fee = TransferFeeSchedule {
+            basis_points: 100,
+            maximum_fee: 50,
+        };
+        let quote = quote(
+            &CONSTANT_PRODUCT,
+            &TradeFee::from_bps(25),
+            true,
+            10_000,
+            Some(&transfer_fee),
+            0,
+        )
+        .unwrap();
+        assert_eq!(quote.expected_out, 19_702);
+    }
+
+    #[test]
+    fn constant_product_empty_reserves() {
+        let reserves = PoolReserves::ConstantProduct {
+            amount_a: 0,
+            amount_b: 2_000_000,
+        };
+        assert_eq!(
+            quote(&reserves, &TradeFee::from_bps(25), true, 10_000, None, 500),
+            None
+        );
+    }
+
+    #[test]
+    fn concentrated_a_to_b() {
+        let quote = quote(
+            &concentrated(),
+            &TradeFee::from_bps(0),
+            true,
+            1_000,
+            None,
+            500,
+        )
+        .unwrap();
+        // sqrt price drops from 2 to 2e6 / 1.002e6, spot out would be 4_000
+        assert_eq!(quote.expected_out, 3_992);
+        assert_eq!(quote.min_amount_out, 3_792);
+    }
+
+    #[test]
+    fn concentrated_b_to_a() {
+        let quote = quote(
+            &concentrated(),
+            &TradeFee::from_bps(0),
+            false,
+            1_000,
+            None,
+            500,
+        )
+        .unwrap();
+        // sqrt price rises from 2 to 2.001, spot out would be 250
+        assert_eq!(quote.expected_out, 249);
+        assert_eq!(quote.min_amount_out, 236);
+    }
+
+    #[test]
+    fn concentrated_trade_fee() {
+        let quote = quote(
+            &concentrated(),
+            &TradeFee::from_bps(100),
+            true,
+            1_000,
+            None,
+            0,
+        )
+        .unwrap();
+        // 990 go in after the fee
+        assert_eq!(quote.expected_out, 3_952);
+    }
+
+    #[test]
+    fn concentrated_without_liquidity() {
+        let reserves = PoolReserves::Concentrated {
+            liquidity: 0,
+            sqrt_price: 2 * Q64 as u128,
+        };
+        assert_eq!(
+            quote(&reserves, &TradeFee::from_bps(0), true, 1_000, None, 500),
+            None
+        );
+    }
+
+    #[test]
+    fn slippage() {
+        assert_eq!(apply_slippage(10_000, 0), 10_000);
+        assert_eq!(apply_slippage(10_000, 500), 9_500);
+        // rounded down
+        assert_eq!(apply_slippage(999, 100), 989);
+        assert_eq!(apply_slippage(10_000, BPS_DENOMINATOR), 0);
+        assert_eq!(apply_slippage(10_000, 20_000), 0);
+        assert_eq!(apply_slippage(u64::MAX, 0), u64::MAX);
+    }
+}
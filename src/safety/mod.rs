@@ -0,0 +1,358 @@
+use crate::config::SafetyConfig;
+use crate::token::TransferFeeSchedule;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::permanent_delegate::PermanentDelegate;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::transfer_hook::TransferHook;
+use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use spl_token_2022::state::Mint;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SafetyRejection {
+    #[error("cannot fetch mint: {0}")]
+    Fetch(String),
+    #[error("mint account not found")]
+    MintNotFound,
+    #[error("account is owned by {0}, not a token program")]
+    NotTokenProgram(Pubkey),
+    #[error("account is not a mint: {0}")]
+    InvalidMint(String),
+    #[error("mint authority {0} is still set")]
+    MintAuthority(Pubkey),
+    #[error("freeze authority {0} is still set")]
+    FreezeAuthority(Pubkey),
+    #[error("token-2022 transfer fee of {0} bps")]
+    TransferFee(u16),
+    #[error("token-2022 transfer fee authority {0} is still set")]
+    TransferFeeAuthority(Pubkey),
+    #[error("token-2022 transfer hook program {0:?}")]
+    TransferHook(Option<Pubkey>),
+    #[error("token-2022 transfer hook authority {0} is still set")]
+    TransferHookAuthority(Pubkey),
+    #[error("token-2022 permanent delegate {0:?}")]
+    PermanentDelegate(Option<Pubkey>),
+    #[error("token-2022 non-transferable mint")]
+    NonTransferable,
+}
+
+// Everything about a mint we need to decide whether and how to buy it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintInfo {
+    pub mint: Pubkey,
+    // spl-token or token-2022
+    pub token_program: Pubkey,
+    pub decimals: u8,
+    pub mint_authority: Option<Pubkey>,
+    pub freeze_authority: Option<Pubkey>,
+    pub transfer_fee: Option<TransferFeeSchedule>,
+    // can raise the transfer fee after we bought, even from 0
+    pub transfer_fee_authority: Option<Pubkey>,
+    pub transfer_hook_program: Option<Pubkey>,
+    // can point the transfer hook at a program later
+    pub transfer_hook_authority: Option<Pubkey>,
+    pub permanent_delegate: Option<Pubkey>,
+    pub extensions: Vec<ExtensionType>,
+}
+
+impl MintInfo {
+    pub fn is_token_2022(&self) -> bool {
+        self.token_program == spl_token_2022::ID
+    }
+}
+
+pub struct SafetyChecker {
+    config: SafetyConfig,
+    rpc_client: Arc<RpcClient>,
+}
+
+impl SafetyChecker {
+    pub fn new(config: SafetyConfig, rpc_client: Arc<RpcClient>) -> Self {
+        SafetyChecker { config, rpc_client }
+    }
+
+    pub async fn fetch_mint(&self, mint: &Pubkey) -> Result<MintInfo, SafetyRejection> {
+        // processed: the mint is usually created a few slots before the pool
+        let account = self
+            .rpc_client
+            .get_account_with_commitment(mint, CommitmentConfig::processed())
+            .await
+            .map_err(|err| SafetyRejection::Fetch(err.to_string()))?
+            .value
+            .ok_or(SafetyRejection::MintNotFound)?;
+
+        parse_mint(mint, &account.owner, &account.data)
+    }
+
+    pub fn check(&self, mint_info: &MintInfo) -> Result<(), SafetyRejection> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        check_mint(&self.config, mint_info)
+    }
+}
+
+// Token-2022 mints share the spl-token base layout, so one parser covers both programs.
+pub fn parse_mint(mint: &Pubkey, owner: &Pubkey, data: &[u8]) -> Result<MintInfo, SafetyRejection> {
+    if *owner != spl_token::ID && *owner != spl_token_2022::ID {
+        return Err(SafetyRejection::NotTokenProgram(*owner));
+    }
+
+    let state = StateWithExtensions::<Mint>::unpack(data)
+        .map_err(|err| SafetyRejection::InvalidMint(err.to_string()))?;
+    let transfer_fee_config = state.get_extension::<TransferFeeConfig>().ok();
+    let transfer_hook = state.get_extension::<TransferHook>().ok();
+
+    Ok(MintInfo {
+        mint: *mint,
+        token_program: *owner,
+        decimals: state.base.decimals,
+        mint_authority: state.base.mint_authority.into(),
+        freeze_authority: state.base.freeze_authority.into(),
+        transfer_fee: transfer_fee_config.and_then(TransferFeeSchedule::from_config),
+        transfer_fee_authority: transfer_fee_config
+            .and_then(|config| config.transfer_fee_config_authority.into()),
+        transfer_hook_program: transfer_hook.and_then(|hook| hook.program_id.into()),
+        transfer_hook_authority: transfer_hook.and_then(|hook| hook.authority.into()),
+        permanent_delegate: state
+            .get_extension::<PermanentDelegate>()
+            .ok()
+            .and_then(|delegate| delegate.delegate.into()),
+        extensions: state.get_extension_types().unwrap_or_default(),
+    })
+}
+
+pub fn check_mint(config: &SafetyConfig, mint_info: &MintInfo) -> Result<(), SafetyRejection> {
+    if config.reject_mint_authority {
+        if let Some(authority) = mint_info.mint_authority {
+            return Err(SafetyRejection::MintAuthority(authority));
+        }
+    }
+    if config.reject_freeze_authority {
+        if let Some(authority) = mint_info.freeze_authority {
+            return Err(SafetyRejection::FreezeAuthority(authority));
+        }
+    }
+    // a live extension authority can turn the extension on after we bought
+    if config.reject_transfer_fee {
+        if let Some(transfer_fee) = mint_info.transfer_fee {
+            return Err(SafetyRejection::TransferFee(transfer_fee.basis_points));
+        }
+        if let Some(authority) = mint_info.transfer_fee_authority {
+            return Err(SafetyRejection::TransferFeeAuthority(authority));
+        }
+    }
+    if config.reject_transfer_hook {
+        if mint_info.transfer_hook_program.is_some() {
+            return Err(SafetyRejection::TransferHook(mint_info.transfer_hook_program));
+        }
+        if let Some(authority) = mint_info.transfer_hook_authority {
+            return Err(SafetyRejection::TransferHookAuthority(authority));
+        }
+    }
+    if config.reject_permanent_delegate && mint_info.permanent_delegate.is_some() {
+        return Err(SafetyRejection::PermanentDelegate(mint_info.permanent_delegate));
+    }
+    if config.reject_non_transferable
+        && mint_info.extensions.contains(&ExtensionType::NonTransferable)
+    {
+        return Err(SafetyRejection::NonTransferable);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_token_2022::extension::{BaseStateWithExtensionsMut, StateWithExtensionsMut};
+
+    fn mint_info() -> MintInfo {
+        MintInfo {
+            mint: Pubkey::new_unique(),
+            token_program: spl_token_2022::ID,
+            decimals: 6,
+            mint_authority: None,
+            freeze_authority: None,
+            transfer_fee: None,
+            transfer_fee_authority: None,
+            transfer_hook_program: None,
+            transfer_hook_authority: None,
+            permanent_delegate: None,
+            extensions: vec![],
+        }
+    }
+
+    // A token-2022 mint without authorities, `init` sets up its extensions.
+    fn token_2022_mint(
+        extensions: &[ExtensionType],
+        init: impl FnOnce(&mut StateWithExtensionsMut<Mint>),
+    ) -> Vec<u8> {
+        let len = ExtensionType::try_calculate_account_len::<Mint>(extensions).unwrap();
+        let mut data = vec![0; len];
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        init(&mut state);
+        state.base = Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        data
+    }
+
+    #[test]
+    fn accepts_renounced_mint() {
+        assert_eq!(check_mint(&SafetyConfig::default(), &mint_info()), Ok(()));
+    }
+
+    #[test]
+    fn rejects_authorities() {
+        let authority = Pubkey::new_unique();
+        let config = SafetyConfig::default();
+
+        let mut mint = mint_info();
+        mint.mint_authority = Some(authority);
+        assert_eq!(
+            check_mint(&config, &mint),
+            Err(SafetyRejection::MintAuthority(authority))
+        );
+
+        let mut mint = mint_info();
+        mint.freeze_authority = Some(authority);
+        assert_eq!(
+            check_mint(&config, &mint),
+            Err(SafetyRejection::FreezeAuthority(authority))
+        );
+    }
+
+    #[test]
+    fn rejects_transfer_fee() {
+        let mut mint = mint_info();
+        mint.transfer_fee = Some(TransferFeeSchedule {
+            basis_points: 100,
+            maximum_fee: u64::MAX,
+        });
+        assert_eq!(
+            check_mint(&SafetyConfig::default(), &mint),
+            Err(SafetyRejection::TransferFee(100))
+        );
+    }
+
+    #[test]
+    fn rejects_live_transfer_fee_authority() {
+        let authority = Pubkey::new_unique();
+        let mut mint = mint_info();
+        mint.transfer_fee_authority = Some(authority);
+        assert_eq!(
+            check_mint(&SafetyConfig::default(), &mint),
+            Err(SafetyRejection::TransferFeeAuthority(authority))
+        );
+    }
+
+    #[test]
+    fn rejects_transfer_hook() {
+        let program = Pubkey::new_unique();
+        let mut mint = mint_info();
+        mint.transfer_hook_program = Some(program);
+        assert_eq!(
+            check_mint(&SafetyConfig::default(), &mint),
+            Err(SafetyRejection::TransferHook(Some(program)))
+        );
+
+        let authority = Pubkey::new_unique();
+        let mut mint = mint_info();
+        mint.transfer_hook_authority = Some(authority);
+        assert_eq!(
+            check_mint(&SafetyConfig::default(), &mint),
+            Err(SafetyRejection::TransferHookAuthority(authority))
+        );
+    }
+
+    #[test]
+    fn rejects_permanent_delegate_and_non_transferable() {
+        let delegate = Pubkey::new_unique();
+        let mut mint = mint_info();
+        mint.permanent_delegate = Some(delegate);
+        assert_eq!(
+            check_mint(&SafetyConfig::default(), &mint),
+            Err(SafetyRejection::PermanentDelegate(Some(delegate)))
+        );
+
+        let mut mint = mint_info();
+        mint.extensions = vec![ExtensionType::NonTransferable];
+        assert_eq!(
+            check_mint(&SafetyConfig::default(), &mint),
+            Err(SafetyRejection::NonTransferable)
+        );
+    }
+
+    #[test]
+    fn disabled_checks_pass() {
+        let config = SafetyConfig {
+            enabled: true,
+            reject_mint_authority: false,
+            reject_freeze_authority: false,
+            reject_transfer_fee: false,
+            reject_transfer_hook: false,
+            reject_permanent_delegate: false,
+            reject_non_transferable: false,
+        };
+        let mint = MintInfo {
+            mint_authority: Some(Pubkey::new_unique()),
+            freeze_authority: Some(Pubkey::new_unique()),
+            transfer_fee_authority: Some(Pubkey::new_unique()),
+            transfer_hook_authority: Some(Pubkey::new_unique()),
+            permanent_delegate: Some(Pubkey::new_unique()),
+            extensions: vec![ExtensionType::NonTransferable],
+            ..mint_info()
+        };
+        assert_eq!(check_mint(&config, &mint), Ok(()));
+    }
+
+    #[test]
+    fn zero_transfer_fee_with_authority() {
+        let authority = Pubkey::new_unique();
+        let data = token_2022_mint(&[ExtensionType::TransferFeeConfig], |state| {
+            let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+            config.transfer_fee_config_authority = Some(authority).try_into().unwrap();
+        });
+
+        let mint = parse_mint(&Pubkey::new_unique(), &spl_token_2022::ID, &data).unwrap();
+        assert_eq!(mint.transfer_fee, None);
+        assert_eq!(mint.transfer_fee_authority, Some(authority));
+        assert_eq!(
+            check_mint(&SafetyConfig::default(), &mint),
+            Err(SafetyRejection::TransferFeeAuthority(authority))
+        );
+    }
+
+    #[test]
+    fn transfer_hook_without_program() {
+        let authority = Pubkey::new_unique();
+        let data = token_2022_mint(&[ExtensionType::TransferHook], |state| {
+            let hook = state.init_extension::<TransferHook>(true).unwrap();
+            hook.authority = Some(authority).try_into().unwrap();
+        });
+
+        let mint = parse_mint(&Pubkey::new_unique(), &spl_token_2022::ID, &data).unwrap();
+        assert_eq!(mint.transfer_hook_program, None);
+        assert_eq!(mint.transfer_hook_authority, Some(authority));
+        assert_eq!(
+            check_mint(&SafetyConfig::default(), &mint),
+            Err(SafetyRejection::TransferHookAuthority(authority))
+        );
+    }
+
+    #[test]
+    fn rejects_other_owners() {
+        let owner = Pubkey::new_unique();
+        assert_eq!(
+            parse_mint(&Pubkey::new_unique(), &owner, &[]),
+            Err(SafetyRejection::NotTokenProgram(owner))
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use spl_token_2022::extension::transfer_fee::{TransferFee, TransferFeeConfig, MAX_FEE_BASIS_POINTS};
+
+// Token-2022 transfer fee withheld from every transfer of a mint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFeeSchedule {
+    pub basis_points: u16,
+    pub maximum_fee: u64,
+}
+
+impl TransferFeeSchedule {
+    // The config holds the fee for the current and for an upcoming epoch; we don't track
+    // epochs, so take whichever is higher to never overestimate what arrives.
+    pub fn from_config(config: &TransferFeeConfig) -> Option<Self> {
+        let fee = |fee: &TransferFee| TransferFeeSchedule {
+            basis_points: u16::from(fee.transfer_fee_basis_points),
+            maximum_fee: u64::from(fee.maximum_fee),
+        };
+        let older = fee(&config.older_transfer_fee);
+        let newer = fee(&config.newer_transfer_fee);
+        let schedule = if newer.basis_points >= older.basis_points {
+            newer
+        } else {
+            older
+        };
+        (schedule.basis_points > 0).then_some(schedule)
+    }
+
+    // fee withheld when `amount` is transferred, rounded up like the token program does
+    pub fn fee(&self, amount: u64) -> u64 {
+        let numerator = amount as u128 * self.basis_points as u128;
+        let fee = numerator.div_ceil(MAX_FEE_BASIS_POINTS as u128);
+        (fee as u64).min(self.maximum_fee)
+    }
+
+    pub fn amount_after_fee(&self, amount: u64) -> u64 {
+        amount.saturating_sub(self.fee(amount))
+    }
+}
+
+// What arrives in the wallet when a transfer of `amount` leaves the pool.
+pub fn amount_received(transfer_fee: Option<&TransferFeeSchedule>, amount: u64) -> u64 {
+    transfer_fee.map_or(amount, |fee| fee.amount_after_fee(amount))
+}
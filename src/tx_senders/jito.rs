@@ -1,34 +1,88 @@
-use crate::config::RpcType;
-use crate::meteora::SwapData;
+use crate::config::{JitoMode, RpcType, TxEncoding};
+use crate::meteora::SwapOrder;
+use crate::tx_senders::constants::JITO_TIP_ADDR;
 use crate::tx_senders::transaction::{build_transaction_with_config, TransactionConfig};
-use crate::tx_senders::{TxResult, TxSender};
+use crate::tx_senders::{BundleStatus, TxResult, TxSender};
 use anyhow::Context;
 use async_trait::async_trait;
 use log::info;
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use solana_sdk::bs58;
 use solana_sdk::hash::Hash;
+use solana_sdk::message::v0::Message;
 use solana_sdk::message::VersionedMessage;
+use solana_sdk::packet::PACKET_DATA_SIZE;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_sdk::system_instruction;
 use solana_sdk::transaction::{Transaction, VersionedTransaction};
-use tracing::debug;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use tracing::{debug, warn};
+
+// bundles tipping less are not considered by the block engine
+const MIN_BUNDLE_TIP: u64 = 1_000;
 
 pub struct JitoTxSender {
     url: String,
+    bundle_url: String,
     name: String,
     client: Client,
     tx_config: TransactionConfig,
+    mode: JitoMode,
+    encoding: TxEncoding,
+    // from getTipAccounts, JITO_TIP_ADDR is used until they arrive
+    tip_accounts: Arc<RwLock<Vec<Pubkey>>>,
+    next_tip_account: AtomicUsize,
 }
 
 impl JitoTxSender {
-    pub fn new(name: String, url: String, tx_config: TransactionConfig, client: Client) -> Self {
+    pub fn new(
+        name: String,
+        url: String,
+        bundle_url: Option<String>,
+        mode: JitoMode,
+        encoding: TxEncoding,
+        tx_config: TransactionConfig,
+        client: Client,
+    ) -> Self {
+        // .../api/v1/transactions -> .../api/v1/bundles, status and tip methods are served there
+        let bundle_url = bundle_url.unwrap_or_else(|| match url.strip_suffix("/transactions") {
+            Some(base) => format!("{}/bundles", base),
+            None => url.clone(),
+        });
+
+        let tip_accounts = Arc::new(RwLock::new(Vec::new()));
+        if mode == JitoMode::Bundle {
+            // fetched up front so the first snipe doesn't wait for them
+            let tip_accounts = tip_accounts.clone();
+            let client = client.clone();
+            let bundle_url = bundle_url.clone();
+            tokio::spawn(async move {
+                match fetch_tip_accounts(&client, &bundle_url).await {
+                    Ok(accounts) => {
+                        info!("{} jito tip accounts from {}", accounts.len(), bundle_url);
+                        *tip_accounts.write().unwrap() = accounts;
+                    }
+                    Err(err) => warn!("cannot fetch jito tip accounts: {:?}", err),
+                }
+            });
+        }
+
         Self {
             url,
+            bundle_url,
             name,
             tx_config,
             client,
+            mode,
+            encoding,
+            tip_accounts,
+            next_tip_account: AtomicUsize::new(0),
         }
     }
 
@@ -36,9 +90,127 @@ impl JitoTxSender {
         &self,
         index: u32,
         recent_blockhash: Hash,
-        swap_data: SwapData,
+        swap_order: SwapOrder,
     ) -> VersionedTransaction {
-        build_transaction_with_config(&self.tx_config, &RpcType::Jito, recent_blockhash, swap_data)
+        build_transaction_with_config(&self.tx_config, &RpcType::Jito, recent_blockhash, swap_order)
+    }
+
+    // Round-robin over the tip accounts, spreading tips avoids write-lock contention on one.
+    fn tip_account(&self) -> Pubkey {
+        let tip_accounts = self.tip_accounts.read().unwrap();
+        if tip_accounts.is_empty() {
+            return Pubkey::from_str(JITO_TIP_ADDR).unwrap();
+        }
+        let index = self.next_tip_account.fetch_add(1, Ordering::Relaxed);
+        tip_accounts[index % tip_accounts.len()]
+    }
+
+    fn tip_transaction(&self, recent_blockhash: Hash) -> anyhow::Result<VersionedTransaction> {
+        let payer = self.tx_config.keypair.pubkey();
+        let tip = system_instruction::transfer(
+            &payer,
+            &self.tip_account(),
+            self.tx_config.tip.max(MIN_BUNDLE_TIP),
+        );
+        let message = Message::try_compile(&payer, &[tip], &[], recent_blockhash)?;
+        Ok(VersionedTransaction::try_new(
+            VersionedMessage::V0(message),
+            &[&self.tx_config.keypair],
+        )?)
+    }
+
+    fn encode(&self, tx: &VersionedTransaction) -> anyhow::Result<String> {
+        let tx_bytes = bincode::serialize(tx).context("cannot serialize tx to bincode")?;
+        if tx_bytes.len() > PACKET_DATA_SIZE {
+            return Err(anyhow::anyhow!(
+                "transaction is {} bytes, over the {} bytes limit",
+                tx_bytes.len(),
+                PACKET_DATA_SIZE
+            ));
+        }
+        Ok(match self.encoding {
+            TxEncoding::Base58 => bs58::encode(tx_bytes).into_string(),
+            TxEncoding::Base64 => base64::encode(tx_bytes),
+        })
+    }
+
+    // base58 is what the block engine assumes when no encoding is given
+    fn params(&self, encoded: Value) -> Value {
+        match self.encoding {
+            TxEncoding::Base58 => json!([encoded]),
+            TxEncoding::Base64 => json!([encoded, { "encoding": "base64" }]),
+        }
+    }
+
+    async fn send_single(
+        &self,
+        index: u32,
+        recent_blockhash: Hash,
+        swap_order: SwapOrder,
+    ) -> anyhow::Result<TxResult> {
+        info!("SEND JITO TX");
+        let tx = self.build_transaction_with_config(index, recent_blockhash, swap_order);
+        let encoded_transaction = self.encode(&tx)?;
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "sendTransaction",
+            "params": self.params(json!(encoded_transaction))
+        });
+        debug!("sending tx: {}", body.to_string());
+        let response = self.client.post(&self.url).json(&body).send().await?;
+        let status = response.status();
+        let bundle_id = response
+            .headers()
+            .get("x-bundle-id")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow::anyhow!("failed to send tx: {}", body));
+        }
+
+        let parsed_resp =
+            serde_json::from_str::<JitoResponse>(&body).context("cannot deserialize signature")?;
+
+        // the result is the transaction signature, the bundle it was wrapped in comes as a header
+        match bundle_id {
+            Some(bundle_id) => Ok(TxResult::BundleID(bundle_id)),
+            None => Ok(TxResult::BundleID(parsed_resp.result)),
+        }
+    }
+
+    // The swap without a tip, then the tip on its own: it is only paid if the swap lands.
+    async fn send_bundle(
+        &self,
+        recent_blockhash: Hash,
+        swap_order: SwapOrder,
+    ) -> anyhow::Result<TxResult> {
+        info!("SEND JITO BUNDLE");
+        let swap_config = TransactionConfig {
+            tip: 0,
+            ..self.tx_config.clone()
+        };
+        let swap_tx = build_transaction_with_config(
+            &swap_config,
+            &RpcType::Jito,
+            recent_blockhash,
+            swap_order,
+        );
+        let bundle = [swap_tx, self.tip_transaction(recent_blockhash)?];
+
+        let encoded_transactions = bundle
+            .iter()
+            .map(|tx| self.encode(tx))
+            .collect::<anyhow::Result<Vec<String>>>()?;
+        let bundle_id: String = jito_request(
+            &self.client,
+            &self.bundle_url,
+            "sendBundle",
+            self.params(json!(encoded_transactions)),
+        )
+        .await?;
+        Ok(TxResult::BundleID(bundle_id))
     }
 }
 
@@ -53,19 +225,35 @@ pub struct JitoBundleStatusResponseInnerValue {
     pub bundle_id: String,
     pub transactions: Vec<String>,
     pub confirmation_status: String,
+    // {"Ok": null} when the bundle's transactions succeeded
     pub err: Value,
 }
 
 #[derive(Deserialize)]
 pub struct JitoBundleStatusResponseInner {
     pub context: JitoBundleStatusResponseInnerContext,
-    pub value: Vec<JitoBundleStatusResponseInnerValue>,
+    // null for bundles the block engine doesn't know
+    pub value: Vec<Option<JitoBundleStatusResponseInnerValue>>,
 }
 #[derive(Deserialize)]
 pub struct JitoBundleStatusResponse {
     pub result: JitoBundleStatusResponseInner,
 }
 
+#[derive(Deserialize)]
+pub struct JitoInflightBundleStatusValue {
+    pub bundle_id: String,
+    // Invalid, Pending, Failed or Landed
+    pub status: String,
+    pub landed_slot: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct JitoInflightBundleStatusInner {
+    pub context: JitoBundleStatusResponseInnerContext,
+    pub value: Vec<JitoInflightBundleStatusValue>,
+}
+
 #[derive(Deserialize)]
 pub struct JitoResponse {
     //bundle id is response
@@ -82,29 +270,92 @@ impl TxSender for JitoTxSender {
         &self,
         index: u32,
         recent_blockhash: Hash,
-        swap_data: SwapData,
+        swap_order: SwapOrder,
     ) -> anyhow::Result<TxResult> {
-        info!("SEND JITO TX");
-        let tx = self.build_transaction_with_config(index, recent_blockhash, swap_data);
-        let tx_bytes = bincode::serialize(&tx).context("cannot serialize tx to bincode")?;
-        let encoded_transaction = bs58::encode(tx_bytes).into_string();
-        let body = json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "sendTransaction",
-            "params": [encoded_transaction]
-        });
-        debug!("sending tx: {}", body.to_string());
-        let response = self.client.post(&self.url).json(&body).send().await?;
-        let status = response.status();
-        let body = response.text().await?;
-        if !status.is_success() {
-            return Err(anyhow::anyhow!("failed to send tx: {}", body));
+        match self.mode {
+            JitoMode::Transaction => self.send_single(index, recent_blockhash, swap_order).await,
+            JitoMode::Bundle => self.send_bundle(recent_blockhash, swap_order).await,
         }
+    }
 
-        let parsed_resp =
-            serde_json::from_str::<JitoResponse>(&body).context("cannot deserialize signature")?;
+    // getInflightBundleStatuses only covers the last five minutes and answers Invalid for
+    // anything older, getBundleStatuses then tells whether it landed.
+    async fn bundle_status(&self, bundle_id: &str) -> anyhow::Result<BundleStatus> {
+        let inflight: JitoInflightBundleStatusInner = jito_request(
+            &self.client,
+            &self.bundle_url,
+            "getInflightBundleStatuses",
+            json!([[bundle_id]]),
+        )
+        .await?;
+        let status = inflight
+            .value
+            .into_iter()
+            .find(|value| value.bundle_id == bundle_id);
 
-        Ok(TxResult::BundleID(parsed_resp.result))
+        match status {
+            Some(status) if status.status == "Pending" => return Ok(BundleStatus::Pending),
+            Some(status) if status.status == "Failed" => return Ok(BundleStatus::Failed),
+            Some(JitoInflightBundleStatusValue {
+                landed_slot: Some(slot),
+                ..
+            }) => return Ok(BundleStatus::Landed(slot)),
+            _ => {}
+        }
+
+        let statuses: JitoBundleStatusResponseInner = jito_request(
+            &self.client,
+            &self.bundle_url,
+            "getBundleStatuses",
+            json!([[bundle_id]]),
+        )
+        .await?;
+        let landed = statuses
+            .value
+            .into_iter()
+            .flatten()
+            .find(|value| value.bundle_id == bundle_id);
+        Ok(match landed {
+            Some(value) if value.err.get("Ok").is_some() => BundleStatus::Landed(value.slot),
+            Some(_) => BundleStatus::Failed,
+            None => BundleStatus::Invalid,
+        })
     }
 }
+
+#[derive(Deserialize)]
+struct JitoRpcResponse<T> {
+    result: T,
+}
+
+async fn jito_request<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    method: &str,
+    params: Value,
+) -> anyhow::Result<T> {
+    let body = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params
+    });
+    let response = client.post(url).json(&body).send().await?;
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        return Err(anyhow::anyhow!("{} failed: {}", method, body));
+    }
+    let response = serde_json::from_str::<JitoRpcResponse<T>>(&body)
+        .context(format!("cannot deserialize {}", method))?;
+    Ok(response.result)
+}
+
+async fn fetch_tip_accounts(client: &Client, bundle_url: &str) -> anyhow::Result<Vec<Pubkey>> {
+    let accounts: Vec<String> =
+        jito_request(client, bundle_url, "getTipAccounts", json!([])).await?;
+    accounts
+        .iter()
+        .map(|account| Pubkey::from_str(account).context("invalid tip account"))
+        .collect()
+}
@@ -1,9 +1,10 @@
 use crate::config::{RpcConfig, RpcType};
-use crate::meteora::SwapData;
+use crate::meteora::SwapOrder;
 use crate::tx_senders::bloxroute::BloxrouteTxSender;
 use crate::tx_senders::jito::JitoTxSender;
 use crate::tx_senders::nextblock::NextblockTxSender;
 use crate::tx_senders::solana_rpc::GenericRpc;
+use crate::tx_senders::tpu::TpuTxSender;
 use crate::tx_senders::transaction::TransactionConfig;
 use async_trait::async_trait;
 use reqwest::Client;
@@ -18,6 +19,7 @@ pub mod constants;
 pub mod jito;
 pub mod nextblock;
 pub mod solana_rpc;
+pub mod tpu;
 pub mod transaction;
 
 #[derive(Debug, Clone)]
@@ -35,6 +37,15 @@ impl Into<String> for TxResult {
     }
 }
 
+// Where a bundle stands, as reported by the block engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleStatus {
+    Landed(u64),
+    Failed,
+    Invalid,
+    Pending,
+}
+
 #[async_trait]
 pub trait TxSender: Sync + Send {
     fn name(&self) -> String;
@@ -42,8 +53,13 @@ pub trait TxSender: Sync + Send {
         &self,
         index: u32,
         recent_blockhash: Hash,
-        swap_data: SwapData
+        swap_order: SwapOrder
     ) -> anyhow::Result<TxResult>;
+
+    // Only senders returning `TxResult::BundleID` can answer this.
+    async fn bundle_status(&self, bundle_id: &str) -> anyhow::Result<BundleStatus> {
+        Err(anyhow::anyhow!("{} does not send bundles, no status for {}", self.name(), bundle_id))
+    }
 }
 
 pub fn create_tx_sender(
@@ -59,7 +75,15 @@ pub fn create_tx_sender(
             Arc::new(tx_sender)
         }
         RpcType::Jito => {
-            let tx_sender = JitoTxSender::new(name, rpc_config.url, tx_config, client);
+            let tx_sender = JitoTxSender::new(
+                name,
+                rpc_config.url,
+                rpc_config.bundle_url,
+                rpc_config.jito_mode,
+                rpc_config.encoding,
+                tx_config,
+                client,
+            );
             Arc::new(tx_sender)
         }
         RpcType::Bloxroute => {
@@ -82,5 +106,9 @@ pub fn create_tx_sender(
             );
             Arc::new(tx_sender)
         }
+        RpcType::Tpu => {
+            let tx_sender = TpuTxSender::new(name, rpc_config.url, rpc_config.tpu, tx_config);
+            Arc::new(tx_sender)
+        }
     }
 }
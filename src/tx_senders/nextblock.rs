@@ -1,5 +1,5 @@
 use crate::config::RpcType;
-use crate::meteora::SwapData;
+use crate::meteora::SwapOrder;
 use crate::tx_senders::transaction::{build_transaction_with_config, TransactionConfig};
 use crate::tx_senders::{TxResult, TxSender};
 use anyhow::Context;
@@ -45,13 +45,13 @@ impl NextblockTxSender {
         &self,
         index: u32,
         recent_blockhash: Hash,
-        swap_data: SwapData
+        swap_order: SwapOrder
     ) -> VersionedTransaction {
         build_transaction_with_config(
             &self.tx_config,
             &RpcType::Bloxroute,
             recent_blockhash,
-            swap_data
+            swap_order
         )
     }
 }
@@ -73,13 +73,13 @@ impl TxSender for NextblockTxSender {
         &self,
         index: u32,
         recent_blockhash: Hash,
-        swap_data: SwapData
+        swap_order: SwapOrder
     ) -> anyhow::Result<TxResult> {
         info!("SEND NEXTBLOCK TX");
         let tx = self.build_transaction_with_config(
             index,
             recent_blockhash,
-            swap_data
+            swap_order
         );
         let tx_bytes = bincode::serialize(&tx).context("cannot serialize tx to bincode")?;
         let encoded_transaction = base64::encode(tx_bytes);
@@ -1,5 +1,5 @@
 use crate::config::RpcType;
-use crate::meteora::SwapData;
+use crate::meteora::SwapOrder;
 use crate::tx_senders::transaction::{build_transaction_with_config, TransactionConfig};
 use crate::tx_senders::{TxResult, TxSender};
 use anyhow::Context;
@@ -55,14 +55,14 @@ impl TxSender for GenericRpc {
         &self,
         index: u32,
         recent_blockhash: Hash,
-        swap_data: SwapData,
+        swap_order: SwapOrder,
     ) -> anyhow::Result<TxResult> {
         println!("SEND SOLANA RPC TX");
         let transaction = build_transaction_with_config(
             &self.tx_config,
             &self.rpc_type,
             recent_blockhash,
-            swap_data,
+            swap_order,
         );
         let sig = self
             .http_rpc
@@ -0,0 +1,241 @@
+use crate::config::{RpcType, TpuConfig};
+use crate::meteora::SwapOrder;
+use crate::tx_senders::transaction::{build_transaction_with_config, TransactionConfig};
+use crate::tx_senders::{TxResult, TxSender};
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::Url;
+use solana_client::connection_cache::ConnectionCache;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::nonblocking::tpu_client::TpuClient;
+use solana_client::tpu_client::{TpuClientConfig, MAX_FANOUT_SLOTS};
+use solana_quic_client::{QuicConfig, QuicConnectionManager, QuicPool};
+use solana_sdk::bs58;
+use solana_sdk::clock::NUM_CONSECUTIVE_LEADER_SLOTS;
+use solana_sdk::hash::Hash;
+use solana_sdk::signature::{Keypair, Signer};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+type QuicTpuClient = TpuClient<QuicPool, QuicConnectionManager, QuicConfig>;
+
+// Sends over QUIC to the TPU of the current and next leaders. The leader schedule and the
+// leaders' TPU addresses come from `rpc_url`, slot updates from its websocket.
+pub struct TpuTxSender {
+    name: String,
+    rpc_url: String,
+    ws_url: String,
+    config: TpuConfig,
+    tx_config: TransactionConfig,
+    // connected in the background, a send before that is done waits for it
+    tpu_client: Arc<OnceCell<QuicTpuClient>>,
+}
+
+impl TpuTxSender {
+    pub fn new(
+        name: String,
+        rpc_url: String,
+        config: TpuConfig,
+        tx_config: TransactionConfig,
+    ) -> Self {
+        let ws_url = config
+            .ws_url
+            .clone()
+            .unwrap_or_else(|| websocket_url(&rpc_url));
+        let tpu_client = Arc::new(OnceCell::new());
+
+        {
+            let tpu_client = tpu_client.clone();
+            let name = name.clone();
+            let rpc_url = rpc_url.clone();
+            let ws_url = ws_url.clone();
+            let config = config.clone();
+            tokio::spawn(async move {
+                let connected = tpu_client
+                    .get_or_try_init(|| connect(&rpc_url, &ws_url, &config))
+                    .await;
+                match connected {
+                    Ok(_) => info!("{} tracking leaders through {}", name, rpc_url),
+                    Err(err) => warn!("{} cannot connect yet: {:?}", name, err),
+                }
+            });
+        }
+
+        TpuTxSender {
+            name,
+            rpc_url,
+            ws_url,
+            config,
+            tx_config,
+            tpu_client,
+        }
+    }
+
+    async fn tpu_client(&self) -> anyhow::Result<&QuicTpuClient> {
+        self.tpu_client
+            .get_or_try_init(|| connect(&self.rpc_url, &self.ws_url, &self.config))
+            .await
+    }
+}
+
+async fn connect(
+    rpc_url: &str,
+    ws_url: &str,
+    config: &TpuConfig,
+) -> anyhow::Result<QuicTpuClient> {
+    // leaders give connections signed by a staked identity a share of their streams
+    let identity = match &config.identity {
+        Some(identity) => decode_keypair(identity).context("invalid tpu identity")?,
+        None => Keypair::new(),
+    };
+    let connection_cache = ConnectionCache::new_with_client_options(
+        "meteora-tpu",
+        1,
+        None,
+        Some((&identity, IpAddr::V4(Ipv4Addr::UNSPECIFIED))),
+        None,
+    );
+    let ConnectionCache::Quic(connection_cache) = connection_cache else {
+        return Err(anyhow::anyhow!("expected a QUIC connection cache"));
+    };
+    info!("tpu identity {}", identity.pubkey());
+
+    let fanout_slots =
+        ((config.next_leaders + 1) * NUM_CONSECUTIVE_LEADER_SLOTS).min(MAX_FANOUT_SLOTS);
+    let rpc_client = Arc::new(RpcClient::new(rpc_url.to_string()));
+    TpuClient::new_with_connection_cache(
+        rpc_client,
+        ws_url,
+        TpuClientConfig { fanout_slots },
+        connection_cache,
+    )
+    .await
+    .context(format!("cannot track leaders through {} / {}", rpc_url, ws_url))
+}
+
+// `Keypair::from_base58_string` panics on anything but a valid keypair.
+fn decode_keypair(encoded: &str) -> anyhow::Result<Keypair> {
+    let bytes = bs58::decode(encoded).into_vec().context("not base58")?;
+    Keypair::from_bytes(&bytes).context("not a 64-byte keypair")
+}
+
+// Like the solana CLI: http -> ws, https -> wss, and an explicit port moves up by one.
+fn websocket_url(rpc_url: &str) -> String {
+    let Ok(mut url) = Url::parse(rpc_url) else {
+        return rpc_url.to_string();
+    };
+    let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+    let _ = url.set_scheme(scheme);
+    if let Some(port) = url.port() {
+        let _ = url.set_port(Some(port + 1));
+    }
+    url.to_string()
+}
+
+#[async_trait]
+impl TxSender for TpuTxSender {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn send_transaction(
+        &self,
+        index: u32,
+        recent_blockhash: Hash,
+        swap_order: SwapOrder,
+    ) -> anyhow::Result<TxResult> {
+        info!("SEND TPU TX");
+        let tx = build_transaction_with_config(
+            &self.tx_config,
+            &RpcType::Tpu,
+            recent_blockhash,
+            swap_order,
+        );
+        let tx_bytes = bincode::serialize(&tx).context("cannot serialize tx to bincode")?;
+
+        // fails only when none of the leaders took it
+        self.tpu_client()
+            .await?
+            .try_send_wire_transaction(tx_bytes)
+            .await
+            .context(format!("Failed to send transaction for {}", self.name))?;
+        Ok(TxResult::Signature(tx.signatures[0]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use solana_sdk::native_token::LAMPORTS_PER_SOL;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::system_transaction;
+    use std::time::Duration;
+
+    const LOCAL_RPC: &str = "http://127.0.0.1:8899";
+
+    #[test]
+    fn websocket_url_from_rpc_url() {
+        assert_eq!(websocket_url(LOCAL_RPC), "ws://127.0.0.1:8900/");
+        assert_eq!(
+            websocket_url("https://api.mainnet-beta.solana.com"),
+            "wss://api.mainnet-beta.solana.com/"
+        );
+    }
+
+    #[test]
+    fn identity() {
+        let keypair = Keypair::new();
+        let decoded = decode_keypair(&keypair.to_base58_string()).unwrap();
+        assert_eq!(decoded.pubkey(), keypair.pubkey());
+
+        assert!(decode_keypair("0OIl").is_err());
+        // a pubkey is not a keypair
+        assert!(decode_keypair(&keypair.pubkey().to_string()).is_err());
+    }
+
+    // Needs a running `solana-test-validator`: cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn sends_to_local_validator() {
+        let rpc_client =
+            RpcClient::new_with_commitment(LOCAL_RPC.to_string(), CommitmentConfig::confirmed());
+        let payer = Keypair::new();
+        let airdrop = rpc_client
+            .request_airdrop(&payer.pubkey(), LAMPORTS_PER_SOL)
+            .await
+            .unwrap();
+        wait_for_confirmation(&rpc_client, &airdrop).await;
+
+        let tpu_client = connect(LOCAL_RPC, &websocket_url(LOCAL_RPC), &TpuConfig::default())
+            .await
+            .unwrap();
+        let recent_blockhash = rpc_client.get_latest_blockhash().await.unwrap();
+        let tx = system_transaction::transfer(
+            &payer,
+            &Pubkey::new_unique(),
+            LAMPORTS_PER_SOL / 10,
+            recent_blockhash,
+        );
+        tpu_client
+            .try_send_wire_transaction(bincode::serialize(&tx).unwrap())
+            .await
+            .unwrap();
+        wait_for_confirmation(&rpc_client, &tx.signatures[0]).await;
+    }
+
+    async fn wait_for_confirmation(
+        rpc_client: &RpcClient,
+        signature: &solana_sdk::signature::Signature,
+    ) {
+        for _ in 0..60 {
+            if rpc_client.confirm_transaction(signature).await.unwrap() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        panic!("{} not confirmed", signature);
+    }
+}
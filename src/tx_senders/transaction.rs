@@ -1,4 +1,4 @@
-use crate::config::{PingThingsArgs, RpcType};
+use crate::config::{PingThingsArgs, RpcType, WsolMode};
 use crate::tx_senders::constants::{
     BLOXROUTE_TIP_ADDR, JITO_TIP_ADDR, METEORA_PROGRAM_ADDR, NEXTBLOCK_BLOCK_TIP_ADDR, RENT_ADDR,
     SYSTEM_PROGRAM_ADDR, TOKEN_PROGRAM_ADDR,
@@ -14,7 +14,9 @@ use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::{EncodableKey, Keypair, Signer};
 use solana_sdk::system_instruction;
 use solana_sdk::transaction::{Transaction, VersionedTransaction};
-use spl_associated_token_account::get_associated_token_address;
+use spl_associated_token_account::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+};
 use spl_associated_token_account::instruction::{
     create_associated_token_account, create_associated_token_account_idempotent,
 };
@@ -22,7 +24,7 @@ use std::str::FromStr;
 use std::sync::Arc;
 use tracing::{error, info, warn};
 
-use crate::meteora::SwapData;
+use crate::meteora::{SwapData, SwapOrder, SwapSide, SwapTarget};
 
 #[derive(Clone)]
 pub struct TransactionConfig {
@@ -31,7 +33,7 @@ pub struct TransactionConfig {
     pub compute_unit_price: u64,
     pub tip: u64,
     pub buy_amount: u64,
-    pub min_amount_out: u64,
+    pub wsol_mode: WsolMode,
 }
 
 impl From<PingThingsArgs> for TransactionConfig {
@@ -40,7 +42,6 @@ impl From<PingThingsArgs> for TransactionConfig {
 
         let tip: u64 = (args.tip * LAMPORTS_PER_SOL as f64) as u64;
         let buy_amount: u64 = (args.buy_amount * LAMPORTS_PER_SOL as f64) as u64;
-        let min_amount_out: u64 = (args.min_amount_out * 1_000_000 as f64) as u64;
 
         TransactionConfig {
             keypair: Arc::new(keypair),
@@ -48,7 +49,7 @@ impl From<PingThingsArgs> for TransactionConfig {
             compute_unit_price: args.compute_unit_price,
             tip: tip,
             buy_amount: buy_amount,
-            min_amount_out: min_amount_out,
+            wsol_mode: args.wsol_mode,
         }
     }
 }
@@ -57,7 +58,7 @@ pub fn build_transaction_with_config(
     tx_config: &TransactionConfig,
     rpc_type: &RpcType,
     recent_blockhash: Hash,
-    swap_data: SwapData,
+    swap_order: SwapOrder,
 ) -> VersionedTransaction {
     let mut instructions = Vec::new();
 
@@ -104,39 +105,121 @@ pub fn build_transaction_with_config(
 
     let owner = tx_config.keypair.pubkey();
 
-    let (user_source_mint, user_destination_mint, protocol_token_fee) =
-        if WSOL_ACCOUNT_ID == swap_data.token_a_mint {
-            // Source is WSOL token A, destination is token B
-            (
-                swap_data.token_a_mint,
-                swap_data.token_b_mint,
-                swap_data.protocol_token_a_fee,
-            )
-        } else {
-            // Source is token B, destination is token A
-            (
-                swap_data.token_b_mint,
-                swap_data.token_a_mint,
-                swap_data.protocol_token_b_fee,
-            )
-        };
+    // one side of the pool is always WSOL, buys pay with it and sells receive it
+    let swap_target = &swap_order.target;
+    let token_mint = swap_target.token_mint();
+    // token-2022 mints live under a different program, their ATAs derive from it
+    let token_mint_program = swap_target.token_program(&token_mint);
 
-    let user_source_token = get_associated_token_address(&owner, &user_source_mint);
-    let user_destination_token = get_associated_token_address(&owner, &user_destination_mint);
+    let wsol_token = get_associated_token_address(&owner, &WSOL_ACCOUNT_ID);
+    let token_account =
+        get_associated_token_address_with_program_id(&owner, &token_mint, &token_mint_program);
 
-    let user_destination_token_ata_instruction = create_associated_token_account_idempotent(
-        &owner,
-        &owner,
-        &user_destination_mint,
-        &token_program_pubkey,
-    );
-    instructions.push(user_destination_token_ata_instruction);
+    let (user_source_mint, user_source_token, user_destination_token) = match swap_order.side {
+        SwapSide::Buy => (WSOL_ACCOUNT_ID, wsol_token, token_account),
+        SwapSide::Sell => (token_mint, token_account, wsol_token),
+    };
+
+    match swap_order.side {
+        SwapSide::Buy => {
+            if tx_config.wsol_mode.wraps() {
+                instructions.extend(wrap_sol_instructions(
+                    &owner,
+                    &wsol_token,
+                    &token_program_pubkey,
+                    swap_order.amount_in,
+                ));
+            }
+
+            instructions.push(create_associated_token_account_idempotent(
+                &owner,
+                &owner,
+                &token_mint,
+                &token_mint_program,
+            ));
+        }
+        // the WSOL account may have been closed after the buy
+        SwapSide::Sell => instructions.push(create_associated_token_account_idempotent(
+            &owner,
+            &owner,
+            &WSOL_ACCOUNT_ID,
+            &token_program_pubkey,
+        )),
+    }
 
     info!("USER SOURCE TOKEN ADDRESS: {:?}", user_source_token);
     info!(
         "USER DESTINATION TOKEN ADDRESS: {:?}",
         user_destination_token
     );
+
+    let swap_instruction = match swap_target {
+        SwapTarget::DynamicAmm(swap_data) => dynamic_amm_swap_instruction(
+            swap_data,
+            owner,
+            user_source_mint,
+            user_source_token,
+            user_destination_token,
+            swap_order.amount_in,
+            swap_order.min_amount_out,
+            token_program_pubkey,
+        ),
+        SwapTarget::Dlmm(swap_data) => swap_data.swap_instruction(
+            owner,
+            user_source_token,
+            user_destination_token,
+            swap_order.amount_in,
+            swap_order.min_amount_out,
+        ),
+        SwapTarget::DammV2(swap_data) => swap_data.swap_instruction(
+            owner,
+            user_source_token,
+            user_destination_token,
+            swap_order.amount_in,
+            swap_order.min_amount_out,
+        ),
+    };
+
+    instructions.push(swap_instruction);
+
+    if tx_config.wsol_mode == WsolMode::WrapAndClose {
+        // returns the rent and any lamports the buy didn't use, or unwraps what the sell received
+        instructions.push(
+            spl_token::instruction::close_account(
+                &token_program_pubkey,
+                &wsol_token,
+                &owner,
+                &owner,
+                &[],
+            )
+            .unwrap(),
+        );
+    }
+
+    let message_v0 =
+        Message::try_compile(&owner, instructions.as_slice(), &[], recent_blockhash).unwrap();
+
+    let versioned_message = VersionedMessage::V0(message_v0);
+
+    VersionedTransaction::try_new(versioned_message, &[&tx_config.keypair]).unwrap()
+}
+
+fn dynamic_amm_swap_instruction(
+    swap_data: &SwapData,
+    owner: Pubkey,
+    user_source_mint: Pubkey,
+    user_source_token: Pubkey,
+    user_destination_token: Pubkey,
+    amount_in: u64,
+    min_amount_out: u64,
+    token_program: Pubkey,
+) -> Instruction {
+    // the protocol fee is taken in the input token
+    let protocol_token_fee = if user_source_mint == swap_data.token_a_mint {
+        swap_data.protocol_token_a_fee
+    } else {
+        swap_data.protocol_token_b_fee
+    };
     info!("PROTOCOL TOKEN FEE: {:?}", protocol_token_fee);
 
     // Swap instruction data
@@ -144,8 +227,8 @@ pub fn build_transaction_with_config(
 
     let mut data = vec![];
     data.extend_from_slice(swap_discriminator);
-    data.extend_from_slice(&tx_config.buy_amount.to_le_bytes());
-    data.extend_from_slice(&tx_config.min_amount_out.to_le_bytes());
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&min_amount_out.to_le_bytes());
 
     let accounts = vec![
         AccountMeta::new(swap_data.pool, false),
@@ -162,21 +245,27 @@ pub fn build_transaction_with_config(
         AccountMeta::new(protocol_token_fee, false),
         AccountMeta::new_readonly(owner, true), // user (signer)
         AccountMeta::new_readonly(swap_data.vault_programm, false),
-        AccountMeta::new_readonly(token_program_pubkey, false),
+        AccountMeta::new_readonly(token_program, false),
     ];
 
-    let swap_instruction = Instruction {
+    Instruction {
         program_id: Pubkey::from_str(METEORA_PROGRAM_ADDR).unwrap(),
         accounts,
         data,
-    };
-
-    instructions.push(swap_instruction);
-
-    let message_v0 =
-        Message::try_compile(&owner, instructions.as_slice(), &[], recent_blockhash).unwrap();
-
-    let versioned_message = VersionedMessage::V0(message_v0);
+    }
+}
 
-    VersionedTransaction::try_new(versioned_message, &[&tx_config.keypair]).unwrap()
+// Creates the WSOL account if needed and funds it with `amount` lamports.
+fn wrap_sol_instructions(
+    owner: &Pubkey,
+    wsol_account: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+) -> Vec<Instruction> {
+    vec![
+        create_associated_token_account_idempotent(owner, owner, &WSOL_ACCOUNT_ID, token_program),
+        system_instruction::transfer(owner, wsol_account, amount),
+        // credits the transferred lamports to the token balance
+        spl_token::instruction::sync_native(token_program, wsol_account).unwrap(),
+    ]
 }